@@ -0,0 +1,79 @@
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use neat_from_scratch::{Neat, NeatConfig, Genome, Evaluator, FitnessFn};
+use neat_from_scratch::test_environments::xor;
+
+const NUM_THREADS:usize = 8;
+
+/// The mpsc based pool which Evaluator replaced, rebuilt for every generation as calculate_fitnesses() did.
+/// It does not seed an eval rng per genome, which only flatters it.
+struct LegacyPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: mpsc::Sender<Option<(usize, FitnessFn)>>,
+}
+
+impl LegacyPool {
+    fn new(size:usize, fitnesses:&Arc<Mutex<Vec<f64>>>, organisms:&Arc<RwLock<Vec<Genome>>>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Option<(usize, FitnessFn)>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..size).map(|_| {
+            let (receiver, fitnesses, organisms) = (Arc::clone(&receiver), Arc::clone(fitnesses), Arc::clone(organisms));
+            thread::spawn(move || loop {
+                let message = receiver.lock().unwrap().recv().unwrap();
+                match message {
+                    Some((i, env)) => {
+                        let fitness = env(&organisms.read().unwrap()[i]);
+                        fitnesses.lock().unwrap()[i] = fitness;
+                    }
+                    None => break,
+                }
+            })
+        }).collect();
+        Self { workers, sender }
+    }
+}
+
+impl Drop for LegacyPool {
+    fn drop(&mut self) {
+        for _ in &self.workers {
+            self.sender.send(None).unwrap();
+        }
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+    }
+}
+
+fn xor_population(size:usize) -> Vec<Genome> {
+    let mut config = NeatConfig::new(2, 1);
+    config.population_size = size;
+    config.seed = Some(0);
+    let neat = Neat::new_fully_connected(config);
+    let organisms = neat.get_population().organisms.read().unwrap().clone();
+    organisms
+}
+
+fn evaluate_xor(c:&mut Criterion) {
+    let organisms = xor_population(1000);
+    let env: FitnessFn = Arc::new(xor);
+
+    let shared_organisms = Arc::new(RwLock::new(organisms.clone()));
+    let fitnesses = Arc::new(Mutex::new(vec![0.0; organisms.len()]));
+    c.bench_function("legacy_pool_xor_1000", |b| b.iter(|| {
+        let pool = LegacyPool::new(NUM_THREADS, &fitnesses, &shared_organisms);
+        for i in 0..organisms.len() {
+            pool.sender.send(Some((i, Arc::clone(&env)))).unwrap();
+        }
+        drop(pool);
+        fitnesses.lock().unwrap()[0]
+    }));
+
+    let evaluator = Evaluator::new(NUM_THREADS, 8);
+    let seeds: Vec<u64> = (0..organisms.len() as u64).collect();
+    c.bench_function("evaluator_xor_1000", |b| b.iter(|| evaluator.evaluate(&organisms, &env, &seeds, None)));
+}
+
+criterion_group!(benches, evaluate_xor);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ndarray::Array2;
+use neat_from_scratch::{Genome, NodeGene, NodeType, ConnectionGene, ActivationFn, FeedForwardNetwork, CompiledNetwork};
+
+/// Builds a genome of fully connected layers, with a bias node feeding every hidden & output node.
+fn layered_genome(layers:&[usize]) -> Genome {
+    let mut genome = Genome::new();
+    let mut innov = 0;
+    let mut layer_ids: Vec<Vec<usize>> = Vec::new();
+    for (depth, size) in layers.iter().enumerate() {
+        let x = depth as f64 / (layers.len() - 1) as f64;
+        let node_type = if depth == 0 { NodeType::Input } else if depth == layers.len() - 1 { NodeType::Output } else { NodeType::Hidden };
+        let activation = if depth == 0 { ActivationFn::Identity } else { ActivationFn::Tanh };
+        layer_ids.push((0..*size).map(|i| {
+            genome.get_nodes_mut().push(NodeGene::new(node_type, innov, x, i as f64 / *size as f64, activation));
+            innov += 1;
+            innov - 1
+        }).collect());
+    }
+    let bias_id = innov;
+    genome.get_nodes_mut().push(NodeGene::new(NodeType::Bias, bias_id, 0.0, 1.0, ActivationFn::Identity));
+
+    let mut conn_innov = 0;
+    for pair in layer_ids.windows(2) {
+        for to in pair[1].iter() {
+            for from in pair[0].iter().chain(std::iter::once(&bias_id)) {
+                let weight = ((conn_innov * 7919) % 200) as f64 / 100.0 - 1.0;
+                genome.get_connections_mut().push(ConnectionGene::new_explicit(conn_innov, weight, true, *from, *to));
+                conn_innov += 1;
+            }
+        }
+    }
+    genome
+}
+
+fn activate(c:&mut Criterion) {
+    let genome = layered_genome(&[8, 32, 32, 4]);
+    let inputs = vec![0.5; 8];
+
+    let mut network = FeedForwardNetwork::new(&genome);
+    c.bench_function("feed_forward_activate", |b| b.iter(|| network.activate(black_box(inputs.clone()))));
+
+    let mut compiled = CompiledNetwork::new(&genome);
+    c.bench_function("compiled_activate", |b| b.iter(|| compiled.activate(black_box(&inputs))[0]));
+
+    let batch = Array2::from_elem((1000, 8), 0.5);
+    c.bench_function("compiled_activate_1000_rows", |b| b.iter(|| {
+        batch.rows().into_iter().map(|row| compiled.activate(black_box(row.as_slice().unwrap()))[0]).sum::<f64>()
+    }));
+    c.bench_function("compiled_activate_batch_1000", |b| b.iter(|| compiled.activate_batch(black_box(&batch))));
+}
+
+criterion_group!(benches, activate);
+criterion_main!(benches);
@@ -1,8 +1,9 @@
-use neat_from_scratch::{Neat, Genome, FeedForwardNetwork, softmax};
+use neat_from_scratch::{Neat, NeatConfig, StdoutReporter, Genome, FeedForwardNetwork, argmax};
 use snake::*;
 
 fn main() {
-    let mut neat = Neat::new_fully_connected();
+    let mut neat = Neat::new_fully_connected(NeatConfig::default());
+    neat.add_reporter(StdoutReporter::new());
     let snake_genome = neat.train(snake_env, 749.0);
     snake_genome.print();
 }
@@ -30,7 +31,7 @@ pub fn snake_env(genome: &Genome) -> f64 {
                 }
                 let input = Vec::from(input);
                 let output = neural_network.activate(input);
-                // action = softmax(output);
+                // action = argmax(output);
 
                 game_turns += 1.0;
                 score
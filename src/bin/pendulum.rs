@@ -1,8 +1,9 @@
 use gym_rs::{ActionType, PendulumEnv, GymEnv, GifRender};
-use neat_from_scratch::{Neat, Genome, FeedForwardNetwork};
+use neat_from_scratch::{Neat, NeatConfig, StdoutReporter, Genome, FeedForwardNetwork};
 
 fn main() {
-    let mut neat = Neat::new_fully_connected();
+    let mut neat = Neat::new_fully_connected(NeatConfig::new(3, 1));
+    neat.add_reporter(StdoutReporter::new());
     let champ = neat.train(pendulum_env, 6000.0);
     render_champion(&champ);
     champ.print();
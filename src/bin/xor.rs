@@ -1,4 +1,4 @@
-use neat_from_scratch::Neat;
+use neat_from_scratch::{Neat, NeatConfig, StdoutReporter};
 use neat_from_scratch::test_environments::xor;
 
 
@@ -17,7 +17,8 @@ numbers are node_ids, connections always from left to right.
 
 
 fn main() {
-    let mut neat = Neat::new_fully_connected();
+    let mut neat = Neat::new_fully_connected(NeatConfig::new(2, 1));
+    neat.add_reporter(StdoutReporter::new());
     let xor_genome = neat.train(xor, 15.9);
     println!("great success!");
     xor_genome.print();
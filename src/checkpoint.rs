@@ -0,0 +1,152 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::Neat;
+
+// Incremented whenever the layout of Neat changes, so stale checkpoints are rejected rather than misread.
+const CHECKPOINT_VERSION:u32 = 1;
+
+/// Returned when a checkpoint cannot be written or read.
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Format(String),
+    Version(u32), // version of the checkpoint file, which this build cannot read
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "checkpoint io error: {}", e),
+            CheckpointError::Format(e) => write!(f, "malformed checkpoint: {}", e),
+            CheckpointError::Version(v) => write!(f, "checkpoint version {} is not supported, expected version {}", v, CHECKPOINT_VERSION),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl Neat {
+
+    /// Saves the entire state of the run, so it can be resumed with Neat::load_checkpoint().
+    /// The checkpoint is written to a temporary file first, so a run killed mid-save never corrupts the previous checkpoint.
+    /// Reporters are not saved.
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path:P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp_path).map_err(CheckpointError::Io)?);
+        bincode::serialize_into(&mut writer, &CHECKPOINT_VERSION).map_err(|e| CheckpointError::Format(e.to_string()))?;
+        bincode::serialize_into(&mut writer, self).map_err(|e| CheckpointError::Format(e.to_string()))?;
+        writer.flush().map_err(CheckpointError::Io)?;
+        drop(writer);
+
+        fs::rename(&tmp_path, path).map_err(CheckpointError::Io)
+    }
+
+    /// Restores a run saved by Neat::save_checkpoint().
+    /// Reporters must be registered again with Neat::add_reporter().
+    pub fn load_checkpoint<P: AsRef<Path>>(path:P) -> Result<Self, CheckpointError> {
+        let mut reader = BufReader::new(File::open(path).map_err(CheckpointError::Io)?);
+        let version: u32 = bincode::deserialize_from(&mut reader).map_err(|e| CheckpointError::Format(e.to_string()))?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::Version(version))
+        }
+        bincode::deserialize_from(&mut reader).map_err(|e| CheckpointError::Format(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use crate::{Neat, NeatConfig, FitnessFn, Reporter, CheckpointError};
+    use crate::test_environments::xor;
+
+    /// A checkpoint path unique to this test process, so concurrent test runs cannot overwrite each other's files.
+    fn temp_path(test:&str) -> PathBuf {
+        std::env::temp_dir().join(format!("neat_checkpoint_{}_{}.bin", test, std::process::id()))
+    }
+
+    #[test]
+    fn checkpoint_roundtrip() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        let mut neat = Neat::new_fully_connected(config);
+        let env: FitnessFn = Arc::new(xor);
+        neat.step(&env);
+        neat.step(&env);
+
+        let path = temp_path("roundtrip");
+        neat.save_checkpoint(&path).unwrap();
+        let loaded = Neat::load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.get_config(), neat.get_config());
+        assert_eq!(loaded.get_node_pool().len(), neat.get_node_pool().len());
+        assert_eq!(loaded.get_connection_pool().len(), neat.get_connection_pool().len());
+        let (p1, p2) = (neat.get_population(), loaded.get_population());
+        assert_eq!(p1.get_generation(), p2.get_generation());
+        assert_eq!(p1.get_fitness_vec(), p2.get_fitness_vec());
+        assert_eq!(p1.get_compatability_threshold(), p2.get_compatability_threshold());
+        assert_eq!(p1.species_len(), p2.species_len());
+        assert_eq!(
+            bincode::serialize(&*p1.organisms.read().unwrap()).unwrap(),
+            bincode::serialize(&*p2.organisms.read().unwrap()).unwrap()
+        );
+    }
+
+    struct FailedCheckpoints(Arc<Mutex<Vec<usize>>>);
+
+    impl Reporter for FailedCheckpoints {
+        fn checkpoint_failed(&mut self, generation:usize, _error:&CheckpointError) { self.0.lock().unwrap().push(generation); }
+    }
+
+    #[test]
+    fn failed_checkpoints_are_reported() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        let mut neat = Neat::new_fully_connected(config);
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        neat.add_reporter(FailedCheckpoints(Arc::clone(&failed)));
+        neat.checkpoint_every(1, temp_path("missing_dir").join("checkpoint.bin"));
+
+        let env: FitnessFn = Arc::new(xor);
+        neat.step(&env);
+        neat.step(&env);
+        assert_eq!(*failed.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn resumed_run_matches_uninterrupted_run() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.seed = Some(11);
+        let env: FitnessFn = Arc::new(xor);
+
+        let mut uninterrupted = Neat::new_fully_connected(config.clone());
+        let mut interrupted = Neat::new_fully_connected(config);
+        for _ in 0..3 {
+            uninterrupted.step(&env);
+            interrupted.step(&env);
+        }
+
+        let path = temp_path("resume");
+        interrupted.save_checkpoint(&path).unwrap();
+        drop(interrupted);
+        let mut resumed = Neat::load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = uninterrupted.step(&env);
+        let actual = resumed.step(&env);
+        assert_eq!(expected.generation, actual.generation);
+        assert_eq!(uninterrupted.get_population().get_fitness_vec(), resumed.get_population().get_fitness_vec());
+        assert_eq!(
+            bincode::serialize(&*uninterrupted.get_population().organisms.read().unwrap()).unwrap(),
+            bincode::serialize(&*resumed.get_population().organisms.read().unwrap()).unwrap()
+        );
+    }
+}
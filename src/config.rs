@@ -1,30 +1,527 @@
-pub const INPUTS:usize = 3;
-pub const OUTPUTS:usize = 3;
-pub const BIAS:bool = true;
-pub const ELITISM:usize = 2;
-pub const POPULATION_SIZE:usize = 1000;
-pub const NUM_THREADS:usize = 4;
+use std::fmt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+use crate::util::{ActivationFn, AggregationFn};
 
 pub const MAX_NODES:usize = (2 as usize).pow(20); // for calculating connection_gene hashcodes
 
-// Speciation hyperparameters
-pub const TARGET_SPECIES_NUM:usize = 50; // The ideal amount of species we would like to have at any given generation
-pub const COMPATABILITY_THRESHOLD:f64 = 8.0;
-pub const COMPATABILITY_MIN:f64 = 0.005;
-pub const COMPATABILITY_MODIFIER:f64 = 0.5; // The amount by which we modify COMPATABILITY_THRESHOLD each generation, whether we are above or below the TARGET_SPECIES_NUM
-pub const DROPOFF_AGE:usize =50; // If a species' fitness does not improve in this many generations, it will be culled.
-pub const C1:f64 = 1.0;
-pub const C2:f64 = 1.0;
-pub const C3:f64 = 0.4;
-
-pub const WEIGHT_RANDOM_MAX:f64 = 5.0; // the absolute maximum value for connection weights
-pub const WEIGHT_SHIFT_MAX_PCT:f64 = 0.1; // The percentage amount by which mutate_weight_shift() modifies connection weights.
-
-// Probabilities for each mutation function
-// pub const M_TOPOLOGY_NODE:f64 = 0.22; // when stagnated, X chance to mutate new node, and 1-X chance to mutate new link (will mutate new node if new link fails)
-pub const M_CONN_ENABLED:f64 = 0.00; // mutate_link_enabled()
-pub const M_WEIGHT_RANDOM:f64 = 0.4; // mutate_weight_random()
-pub const M_WEIGHT_SHIFT:f64 = 0.8; // mutate_weight_shift()
-pub const M_NODE:f64 = 0.02; // mutate_new_node()
-pub const M_CONN:f64 = 0.1; // mutate_new_connection()
-pub const MAX_ITER:usize = 100; // Max iterations for functions which loop until valid value
\ No newline at end of file
+/// Every hyperparameter used to run NEAT.
+/// Construct with NeatConfig::new(inputs, outputs) and override any field before passing it to Neat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeatConfig {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub bias: bool,
+    pub elitism: usize,
+    pub population_size: usize,
+    pub num_threads: usize,
+    pub eval_chunk_size: usize, // the fewest genomes an evaluation thread takes at once. Raise it for cheap fitness functions
+    pub eval_timeout: Option<f64>, // seconds a genome's evaluation may take before it fails. Each evaluation then runs on its own thread
+    pub failed_fitness: f64, // given to genomes whose fitness function panicked or timed out
+    pub max_concurrent_evals: usize, // the most async fitness evaluations in flight at once. eval_timeout does not apply to async evaluation
+    pub seed: Option<u64>, // seeds every random decision. When None, Neat picks a seed at random and stores it here.
+    pub recurrent: bool, // Allow back edges & self loops. Genomes must then be evaluated with a RecurrentNetwork rather than a FeedForwardNetwork.
+
+    // Speciation hyperparameters
+    pub target_species_num: usize, // The ideal amount of species we would like to have at any given generation
+    pub compatability_threshold: f64,
+    pub compatability_min: f64,
+    pub compatability_modifier: f64, // The amount by which we modify compatability_threshold each generation, whether we are above or below the target_species_num
+    pub dropoff_age: usize, // If a species' fitness does not improve in this many generations, it will be culled.
+    pub c1: f64,
+    pub c2: f64,
+    pub c3: f64,
+    pub c4: f64, // weighting of activation & aggregation function mismatches between matching nodes
+
+    pub weight_random_max: f64, // the absolute maximum value for connection weights
+    pub weight_shift_max_pct: f64, // The percentage amount by which mutate_weight_shift() modifies connection weights.
+
+    // Activation functions
+    pub hidden_activation: ActivationFn, // given to every new hidden node
+    pub output_activation: ActivationFn,
+    pub activation_options: Vec<ActivationFn>, // The functions mutate_activation() chooses between
+
+    // Aggregation functions
+    pub hidden_aggregation: AggregationFn, // given to every new hidden node
+    pub output_aggregation: AggregationFn,
+    pub aggregation_options: Vec<AggregationFn>, // The functions mutate_aggregation() chooses between
+
+    // Per-node bias & response, as in neat-python
+    pub node_parameters: bool, // Evolve a bias & response for every hidden and output node. Can be used alongside or instead of the bias input node.
+    pub bias_random_max: f64, // the absolute maximum value mutate_bias_random() gives a node's bias
+    pub bias_perturb_max: f64, // the maximum amount by which mutate_bias_perturb() shifts a node's bias
+    pub response_random_max: f64, // the maximum distance from 1 mutate_response_random() gives a node's response
+    pub response_perturb_max: f64, // the maximum amount by which mutate_response_perturb() shifts a node's response
+
+    // Per-node time constants, only used by Ctrnn
+    pub time_constant_min: f64, // the smallest time constant mutate_time_constant() gives a node. Keep it above the step size passed to Ctrnn::advance()
+    pub time_constant_max: f64, // the largest time constant mutate_time_constant() gives a node
+    pub time_constant_perturb_max: f64, // the maximum amount by which mutate_time_constant() shifts a node's time constant
+
+    // Probabilities for each mutation function
+    pub m_conn_enabled: f64, // mutate_link_enabled()
+    pub m_weight_random: f64, // mutate_weight_random()
+    pub m_weight_shift: f64, // mutate_weight_shift()
+    pub m_node: f64, // mutate_new_node()
+    pub m_conn: f64, // mutate_new_connection()
+    pub m_activation: f64, // mutate_activation()
+    pub m_aggregation: f64, // mutate_aggregation()
+    pub m_bias_perturb: f64, // mutate_bias_perturb(), only when node_parameters is set
+    pub m_bias_random: f64, // mutate_bias_random(), only when node_parameters is set
+    pub m_response_perturb: f64, // mutate_response_perturb(), only when node_parameters is set
+    pub m_response_random: f64, // mutate_response_random(), only when node_parameters is set
+    pub m_time_constant: f64, // mutate_time_constant()
+    pub max_iter: usize, // Max iterations for functions which loop until valid value
+
+    pub validate_genomes: bool, // Debug mode: validate every genome after each phase, panicking on the first invalid one
+}
+
+impl NeatConfig {
+
+    /// Constructs a config for networks with the given number of inputs and outputs.
+    /// Every other hyperparameter takes its default value.
+    pub fn new(inputs:usize, outputs:usize) -> Self {
+        Self {
+            inputs,
+            outputs,
+            ..Self::default()
+        }
+    }
+
+    /// Reads a config from a TOML file. See NeatConfig::from_toml_str() for the format.
+    pub fn from_file<P: AsRef<Path>>(path:P) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml_str(&contents)
+    }
+
+    /// Parses a config from TOML where each key is a field of NeatConfig, e.g.
+    /// ```toml
+    /// inputs = 2
+    /// outputs = 1
+    /// population_size = 150
+    /// m_node = 0.03
+    /// ```
+    /// Keys are case-insensitive, so TARGET_SPECIES_NUM and target_species_num are equivalent.
+    /// Omitted keys take their default value.
+    /// Every unknown key, mistyped value and out-of-range value is reported in the returned error.
+    pub fn from_toml_str(contents:&str) -> Result<Self, ConfigError> {
+        let table: toml::Table = contents.parse().map_err(|e: toml::de::Error| ConfigError::Parse(e.to_string()))?;
+        let mut config = Self::default();
+        let mut problems = Vec::new();
+        let mut seen: HashMap<String, &str> = HashMap::new(); // lowercased key -> the key as written
+
+        for (key, value) in table.iter() {
+            let lowercase = key.to_lowercase();
+            if let Some(first) = seen.insert(lowercase.clone(), key) {
+                problems.push(format!("'{}' and '{}' are the same key", first, key));
+                continue
+            }
+            match lowercase.as_str() {
+                "inputs" => read_usize(key, value, &mut config.inputs, &mut problems),
+                "outputs" => read_usize(key, value, &mut config.outputs, &mut problems),
+                "bias" => read_bool(key, value, &mut config.bias, &mut problems),
+                "elitism" => read_usize(key, value, &mut config.elitism, &mut problems),
+                "population_size" => read_usize(key, value, &mut config.population_size, &mut problems),
+                "num_threads" => read_usize(key, value, &mut config.num_threads, &mut problems),
+                "eval_chunk_size" => read_usize(key, value, &mut config.eval_chunk_size, &mut problems),
+                "eval_timeout" => {
+                    let mut timeout = 0.0;
+                    read_f64(key, value, &mut timeout, &mut problems);
+                    config.eval_timeout = Some(timeout);
+                }
+                "failed_fitness" => read_f64(key, value, &mut config.failed_fitness, &mut problems),
+                "max_concurrent_evals" => read_usize(key, value, &mut config.max_concurrent_evals, &mut problems),
+                "seed" => {
+                    let mut seed = 0;
+                    read_usize(key, value, &mut seed, &mut problems);
+                    config.seed = Some(seed as u64);
+                }
+                "recurrent" => read_bool(key, value, &mut config.recurrent, &mut problems),
+                "target_species_num" => read_usize(key, value, &mut config.target_species_num, &mut problems),
+                "compatability_threshold" => read_f64(key, value, &mut config.compatability_threshold, &mut problems),
+                "compatability_min" => read_f64(key, value, &mut config.compatability_min, &mut problems),
+                "compatability_modifier" => read_f64(key, value, &mut config.compatability_modifier, &mut problems),
+                "dropoff_age" => read_usize(key, value, &mut config.dropoff_age, &mut problems),
+                "c1" => read_f64(key, value, &mut config.c1, &mut problems),
+                "c2" => read_f64(key, value, &mut config.c2, &mut problems),
+                "c3" => read_f64(key, value, &mut config.c3, &mut problems),
+                "c4" => read_f64(key, value, &mut config.c4, &mut problems),
+                "weight_random_max" => read_f64(key, value, &mut config.weight_random_max, &mut problems),
+                "weight_shift_max_pct" => read_f64(key, value, &mut config.weight_shift_max_pct, &mut problems),
+                "hidden_activation" => read_activation(key, value, &mut config.hidden_activation, &mut problems),
+                "output_activation" => read_activation(key, value, &mut config.output_activation, &mut problems),
+                "activation_options" => read_activations(key, value, &mut config.activation_options, &mut problems),
+                "hidden_aggregation" => read_aggregation(key, value, &mut config.hidden_aggregation, &mut problems),
+                "output_aggregation" => read_aggregation(key, value, &mut config.output_aggregation, &mut problems),
+                "aggregation_options" => read_aggregations(key, value, &mut config.aggregation_options, &mut problems),
+                "node_parameters" => read_bool(key, value, &mut config.node_parameters, &mut problems),
+                "bias_random_max" => read_f64(key, value, &mut config.bias_random_max, &mut problems),
+                "bias_perturb_max" => read_f64(key, value, &mut config.bias_perturb_max, &mut problems),
+                "response_random_max" => read_f64(key, value, &mut config.response_random_max, &mut problems),
+                "response_perturb_max" => read_f64(key, value, &mut config.response_perturb_max, &mut problems),
+                "time_constant_min" => read_f64(key, value, &mut config.time_constant_min, &mut problems),
+                "time_constant_max" => read_f64(key, value, &mut config.time_constant_max, &mut problems),
+                "time_constant_perturb_max" => read_f64(key, value, &mut config.time_constant_perturb_max, &mut problems),
+                "m_conn_enabled" => read_f64(key, value, &mut config.m_conn_enabled, &mut problems),
+                "m_weight_random" => read_f64(key, value, &mut config.m_weight_random, &mut problems),
+                "m_weight_shift" => read_f64(key, value, &mut config.m_weight_shift, &mut problems),
+                "m_node" => read_f64(key, value, &mut config.m_node, &mut problems),
+                "m_conn" => read_f64(key, value, &mut config.m_conn, &mut problems),
+                "m_activation" => read_f64(key, value, &mut config.m_activation, &mut problems),
+                "m_aggregation" => read_f64(key, value, &mut config.m_aggregation, &mut problems),
+                "m_bias_perturb" => read_f64(key, value, &mut config.m_bias_perturb, &mut problems),
+                "m_bias_random" => read_f64(key, value, &mut config.m_bias_random, &mut problems),
+                "m_response_perturb" => read_f64(key, value, &mut config.m_response_perturb, &mut problems),
+                "m_response_random" => read_f64(key, value, &mut config.m_response_random, &mut problems),
+                "m_time_constant" => read_f64(key, value, &mut config.m_time_constant, &mut problems),
+                "max_iter" => read_usize(key, value, &mut config.max_iter, &mut problems),
+                "validate_genomes" => read_bool(key, value, &mut config.validate_genomes, &mut problems),
+                _ => problems.push(format!("unknown key '{}'", key)),
+            }
+        }
+
+        problems.extend(config.problems());
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Checks every hyperparameter is within its valid range.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Returns a description of every out-of-range hyperparameter.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.inputs == 0 { problems.push(String::from("inputs must be at least 1")); }
+        if self.outputs == 0 { problems.push(String::from("outputs must be at least 1")); }
+        if self.population_size == 0 { problems.push(String::from("population_size must be at least 1")); }
+        if self.num_threads == 0 { problems.push(String::from("num_threads must be at least 1")); }
+        if self.eval_chunk_size == 0 { problems.push(String::from("eval_chunk_size must be at least 1")); }
+        if self.max_concurrent_evals == 0 { problems.push(String::from("max_concurrent_evals must be at least 1")); }
+        if let Some(timeout) = self.eval_timeout {
+            if !(timeout.is_finite() && timeout > 0.0) {
+                problems.push(format!("eval_timeout must be a positive number of seconds, got {}", timeout));
+            }
+        }
+        if !self.failed_fitness.is_finite() {
+            problems.push(format!("failed_fitness must be finite, got {}", self.failed_fitness));
+        }
+        if self.target_species_num == 0 { problems.push(String::from("target_species_num must be at least 1")); }
+        if self.max_iter == 0 { problems.push(String::from("max_iter must be at least 1")); }
+        if self.elitism > self.population_size {
+            problems.push(format!("elitism ({}) must not exceed population_size ({})", self.elitism, self.population_size));
+        }
+
+        let non_negative = [
+            ("compatability_threshold", self.compatability_threshold),
+            ("compatability_min", self.compatability_min),
+            ("compatability_modifier", self.compatability_modifier),
+            ("c1", self.c1),
+            ("c2", self.c2),
+            ("c3", self.c3),
+            ("c4", self.c4),
+            ("weight_random_max", self.weight_random_max),
+            ("weight_shift_max_pct", self.weight_shift_max_pct),
+            ("bias_random_max", self.bias_random_max),
+            ("bias_perturb_max", self.bias_perturb_max),
+            ("response_random_max", self.response_random_max),
+            ("response_perturb_max", self.response_perturb_max),
+            ("time_constant_perturb_max", self.time_constant_perturb_max),
+        ];
+        for (name, value) in non_negative {
+            if !value.is_finite() || value < 0.0 {
+                problems.push(format!("{} must be a non-negative number, got {}", name, value));
+            }
+        }
+        if !(self.time_constant_min.is_finite() && self.time_constant_min > 0.0) {
+            problems.push(format!("time_constant_min must be above 0, got {}", self.time_constant_min));
+        }
+        if !self.time_constant_max.is_finite() || self.time_constant_max < self.time_constant_min {
+            problems.push(format!("time_constant_max ({}) must be finite and not below time_constant_min ({})", self.time_constant_max, self.time_constant_min));
+        }
+        if self.compatability_min > self.compatability_threshold {
+            problems.push(format!("compatability_min ({}) must not exceed compatability_threshold ({})", self.compatability_min, self.compatability_threshold));
+        }
+
+        let probabilities = [
+            ("m_conn_enabled", self.m_conn_enabled),
+            ("m_weight_random", self.m_weight_random),
+            ("m_weight_shift", self.m_weight_shift),
+            ("m_node", self.m_node),
+            ("m_conn", self.m_conn),
+            ("m_activation", self.m_activation),
+            ("m_aggregation", self.m_aggregation),
+            ("m_bias_perturb", self.m_bias_perturb),
+            ("m_bias_random", self.m_bias_random),
+            ("m_response_perturb", self.m_response_perturb),
+            ("m_response_random", self.m_response_random),
+            ("m_time_constant", self.m_time_constant),
+        ];
+        for (name, value) in probabilities {
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!("{} is a probability and must be between 0 and 1, got {}", name, value));
+            }
+        }
+        if self.m_activation > 0.0 && self.activation_options.is_empty() {
+            problems.push(String::from("activation_options must not be empty when m_activation is above 0"));
+        }
+        if self.m_aggregation > 0.0 && self.aggregation_options.is_empty() {
+            problems.push(String::from("aggregation_options must not be empty when m_aggregation is above 0"));
+        }
+
+        problems
+    }
+
+    /// Returns the number of input, bias & output nodes, which are the first innovation numbers in every genome.
+    pub fn num_ibo(&self) -> usize {
+        self.inputs + self.bias as usize + self.outputs
+    }
+}
+
+impl Default for NeatConfig {
+    fn default() -> Self {
+        Self {
+            inputs: 3,
+            outputs: 3,
+            bias: true,
+            elitism: 2,
+            population_size: 1000,
+            num_threads: 4,
+            eval_chunk_size: 8,
+            eval_timeout: None,
+            failed_fitness: 0.0,
+            max_concurrent_evals: 64,
+            seed: None,
+            recurrent: false,
+
+            target_species_num: 50,
+            compatability_threshold: 8.0,
+            compatability_min: 0.005,
+            compatability_modifier: 0.5,
+            dropoff_age: 50,
+            c1: 1.0,
+            c2: 1.0,
+            c3: 0.4,
+            c4: 0.5,
+
+            weight_random_max: 5.0,
+            weight_shift_max_pct: 0.1,
+
+            hidden_activation: ActivationFn::Sigmoid,
+            output_activation: ActivationFn::Sigmoid,
+            activation_options: ActivationFn::ALL.to_vec(),
+
+            hidden_aggregation: AggregationFn::Sum,
+            output_aggregation: AggregationFn::Sum,
+            aggregation_options: AggregationFn::ALL.to_vec(),
+
+            node_parameters: false,
+            bias_random_max: 2.0,
+            bias_perturb_max: 0.5,
+            response_random_max: 1.0,
+            response_perturb_max: 0.1,
+
+            time_constant_min: 0.1,
+            time_constant_max: 5.0,
+            time_constant_perturb_max: 0.1,
+
+            m_conn_enabled: 0.00,
+            m_weight_random: 0.4,
+            m_weight_shift: 0.8,
+            m_node: 0.02,
+            m_conn: 0.1,
+            m_activation: 0.0,
+            m_aggregation: 0.0,
+            m_bias_perturb: 0.7,
+            m_bias_random: 0.1,
+            m_response_perturb: 0.1,
+            m_response_random: 0.0,
+            m_time_constant: 0.0,
+            max_iter: 100,
+
+            validate_genomes: false,
+        }
+    }
+}
+
+/// Returned when a config file cannot be read, parsed or contains invalid hyperparameters.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(String),
+    Invalid(Vec<String>), // Every problem found in the config
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config file: {}", e),
+            ConfigError::Parse(e) => write!(f, "could not parse config file: {}", e),
+            ConfigError::Invalid(problems) => {
+                writeln!(f, "invalid config, {} problem(s) found:", problems.len())?;
+                for problem in problems {
+                    writeln!(f, "  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn read_usize(key:&str, value:&toml::Value, field:&mut usize, problems:&mut Vec<String>) {
+    match value.as_integer() {
+        Some(v) if v >= 0 => *field = v as usize,
+        Some(v) => problems.push(format!("{} must be a non-negative integer, got {}", key, v)),
+        None => problems.push(format!("{} must be an integer, got {}", key, value)),
+    }
+}
+
+fn read_f64(key:&str, value:&toml::Value, field:&mut f64, problems:&mut Vec<String>) {
+    match value {
+        toml::Value::Float(v) => *field = *v,
+        toml::Value::Integer(v) => *field = *v as f64,
+        _ => problems.push(format!("{} must be a number, got {}", key, value)),
+    }
+}
+
+fn read_bool(key:&str, value:&toml::Value, field:&mut bool, problems:&mut Vec<String>) {
+    match value.as_bool() {
+        Some(v) => *field = v,
+        None => problems.push(format!("{} must be true or false, got {}", key, value)),
+    }
+}
+
+fn read_activation(key:&str, value:&toml::Value, field:&mut ActivationFn, problems:&mut Vec<String>) {
+    match value.as_str().map(|name| name.parse::<ActivationFn>()) {
+        Some(Ok(activation)) => *field = activation,
+        Some(Err(e)) => problems.push(format!("{}: {}", key, e)),
+        None => problems.push(format!("{} must be the name of an activation function, got {}", key, value)),
+    }
+}
+
+fn read_activations(key:&str, value:&toml::Value, field:&mut Vec<ActivationFn>, problems:&mut Vec<String>) {
+    match value.as_array() {
+        Some(names) => {
+            let mut activations = Vec::new();
+            for name in names {
+                let mut activation = ActivationFn::Sigmoid;
+                let num_problems = problems.len();
+                read_activation(key, name, &mut activation, problems);
+                if problems.len() == num_problems {
+                    activations.push(activation);
+                }
+            }
+            *field = activations;
+        }
+        None => problems.push(format!("{} must be an array of activation function names, got {}", key, value)),
+    }
+}
+
+fn read_aggregation(key:&str, value:&toml::Value, field:&mut AggregationFn, problems:&mut Vec<String>) {
+    match value.as_str().map(|name| name.parse::<AggregationFn>()) {
+        Some(Ok(aggregation)) => *field = aggregation,
+        Some(Err(e)) => problems.push(format!("{}: {}", key, e)),
+        None => problems.push(format!("{} must be the name of an aggregation function, got {}", key, value)),
+    }
+}
+
+fn read_aggregations(key:&str, value:&toml::Value, field:&mut Vec<AggregationFn>, problems:&mut Vec<String>) {
+    match value.as_array() {
+        Some(names) => {
+            let mut aggregations = Vec::new();
+            for name in names {
+                let mut aggregation = AggregationFn::Sum;
+                let num_problems = problems.len();
+                read_aggregation(key, name, &mut aggregation, problems);
+                if problems.len() == num_problems {
+                    aggregations.push(aggregation);
+                }
+            }
+            *field = aggregations;
+        }
+        None => problems.push(format!("{} must be an array of aggregation function names, got {}", key, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{ActivationFn, AggregationFn};
+    use super::{NeatConfig, ConfigError};
+
+    #[test]
+    fn from_toml_str_overrides_defaults() {
+        let config = NeatConfig::from_toml_str("
+            inputs = 2
+            outputs = 1
+            POPULATION_SIZE = 150
+            c3 = 1
+            m_node = 0.03
+            hidden_activation = 'tanh'
+            activation_options = ['relu', 'sin']
+            output_aggregation = 'max'
+        ").unwrap();
+        assert_eq!(config.inputs, 2);
+        assert_eq!(config.outputs, 1);
+        assert_eq!(config.population_size, 150);
+        assert_eq!(config.c3, 1.0);
+        assert_eq!(config.m_node, 0.03);
+        assert_eq!(config.hidden_activation, ActivationFn::Tanh);
+        assert_eq!(config.activation_options, vec![ActivationFn::Relu, ActivationFn::Sin]);
+        assert_eq!(config.output_aggregation, AggregationFn::Max);
+        assert_eq!(config.elitism, NeatConfig::default().elitism);
+    }
+
+    #[test]
+    fn from_toml_str_rejects_keys_differing_in_case() {
+        match NeatConfig::from_toml_str("Pop_Size = 10\npopulation_size = 100\nPOPULATION_SIZE = 150") {
+            Err(ConfigError::Invalid(problems)) => assert!(problems.iter().any(|p| p.contains("population_size") && p.contains("POPULATION_SIZE"))),
+            _ => panic!("expected an invalid config"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "population_size must be at least 1")]
+    fn neat_rejects_invalid_configs() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 0;
+        crate::Neat::new(config);
+    }
+
+    #[test]
+    fn from_toml_str_reports_every_problem() {
+        let res = NeatConfig::from_toml_str("
+            m_conn = -0.1
+            target_species_num = 0
+            compatability_min = 10.0
+            compatability_threshold = 8.0
+            mutation_rate = 0.5
+            bias = 1
+        ");
+        match res {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 5);
+                assert!(problems.iter().any(|p| p.contains("m_conn")));
+                assert!(problems.iter().any(|p| p.contains("target_species_num")));
+                assert!(problems.iter().any(|p| p.contains("compatability_min")));
+                assert!(problems.iter().any(|p| p.contains("mutation_rate")));
+                assert!(problems.iter().any(|p| p.contains("bias")));
+            }
+            _ => panic!("expected an invalid config"),
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use rand::prelude::*;
+use serde::{Serialize, Deserialize};
 
-use crate::config::WEIGHT_RANDOM_MAX;
+use crate::config::NeatConfig;
 use super::gene::Gene;
 use super::conn_hashcode;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionGene {
     innovation_number: usize,
     weight: f64,
@@ -20,12 +21,12 @@ impl Gene for ConnectionGene {
 impl ConnectionGene {
 
     /// Constructs a new ConnectionGene from a specified node to a specified node
-    /// weight is random between -WEIGHT_RANDOM_MAX to WEIGHT_RANDOM_MAX
+    /// weight is random between -config.weight_random_max to config.weight_random_max
     /// enabled: true
-    pub fn new(innovation_number:usize, from:usize, to:usize) -> Self {
+    pub fn new<R: Rng + ?Sized>(innovation_number:usize, from:usize, to:usize, config:&NeatConfig, rng:&mut R) -> Self {
         Self {
             innovation_number,
-            weight: rand::thread_rng().gen_range(-1.0..1.0) * WEIGHT_RANDOM_MAX,
+            weight: rng.gen_range(-1.0..1.0) * config.weight_random_max,
             enabled: true,
             from,
             to,        
@@ -1,13 +1,14 @@
-use std::collections::HashMap;
-use ndarray::Array2;
+use std::collections::{HashMap, HashSet};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
 
-use crate::config::{INPUTS, BIAS, OUTPUTS};
+use crate::config::NeatConfig;
 use crate::util::VecSet;
 
 use super::{ConnectionGene, NodeGene, conn_hashcode};
 
 ///An encoding for a neural network, which provides mutation, crossover & speciation functionality
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Genome {
     nodes: VecSet<NodeGene>, 
     connections: VecSet<ConnectionGene>,
@@ -24,12 +25,12 @@ impl Genome {
     }
 
     /// Constructs a new Genome with input, bias & output nodes, but no connections.
-    pub fn new_init(node_pool:&HashMap<usize, NodeGene>) -> Self {
+    pub fn new_init(node_pool:&HashMap<usize, NodeGene>, config:&NeatConfig) -> Self {
         Self {
             nodes: {
                 // Store a clone of input, bias and outputs nodes
                 let mut init_nodes = VecSet::new();
-                for i in 0..(INPUTS + BIAS as usize + OUTPUTS) {
+                for i in 0..config.num_ibo() {
                     init_nodes.push(node_pool.get(&i).unwrap().clone());
                 }
                 init_nodes
@@ -40,19 +41,19 @@ impl Genome {
     
     /// Constructs a new Genome with input, bias & output nodes.
     /// Each input and bias node has a connection to each output node.
-    pub fn new_fully_connected(node_pool: &HashMap<usize, NodeGene>, conn_pool: &HashMap<usize, ConnectionGene>) -> Self {
+    pub fn new_fully_connected(node_pool: &HashMap<usize, NodeGene>, conn_pool: &HashMap<usize, ConnectionGene>, config:&NeatConfig) -> Self {
         Self {
             nodes: {
                 let mut init_nodes = VecSet::new();
-                for i in 0..(INPUTS + BIAS as usize + OUTPUTS) {
+                for i in 0..config.num_ibo() {
                     init_nodes.push(node_pool.get(&i).unwrap().clone());
                 }
                 init_nodes
             },
             connections: {
                 let mut init_conns = VecSet::new();
-                for from in 0..(INPUTS + BIAS as usize) {
-                    for to in (INPUTS + BIAS as usize)..(INPUTS + BIAS as usize + OUTPUTS) {
+                for from in 0..(config.inputs + config.bias as usize) {
+                    for to in (config.inputs + config.bias as usize)..config.num_ibo() {
                         init_conns.push(conn_pool.get(&conn_hashcode(from, to))
                         .unwrap().clone());
                     }
@@ -62,64 +63,22 @@ impl Genome {
         }
     }
 
-    /// Returns the adjacency matrix of the genome's nodes.
-    /// Connections are one-way.
-    /// Row corresponds to 'from' & column to 'to' node.
-    fn adjacency_matrix(&self) -> Array2<u8> {
-        if let Some(max_innov) = self.nodes.max_innov() {
-            let mut res = Array2::<u8>::zeros((max_innov + 1, max_innov + 1));
-            for connection in self.connections.iter() {
-                let from = connection.get_from();
-                let to = connection.get_to();
-                res[(from, to)] = 1;
-            }
-            return res
-        } else {
-            return Array2::<u8>::zeros((0,0));
-        }
-    }
-
-    /// Checks whether a connection already exists between some 'from_node' to some 'to_node'
-    /// Utilises recursive wizardry
+    /// Checks whether a path of connections, enabled or not, already leads from some 'from_node' to some 'to_node'
+    /// Searches depth first from 'from_node', so it also terminates on recurrent genomes.
     pub fn is_connected(&self, from_node_innov:usize, to_node_innov:usize) -> bool {
-        let adjacency_matrix = self.adjacency_matrix();
-        for to in 0..self.nodes.len() {
-            if adjacency_matrix[(from_node_innov, to)] == 1 {
-                if to == to_node_innov {
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut frontier = vec![from_node_innov];
+        while let Some(node) = frontier.pop() {
+            for connection in self.connections.iter().filter(|connection| connection.get_from() == node) {
+                if connection.get_to() == to_node_innov {
                     return true
-                } else {
-                    if self.is_connected(to, to_node_innov) == true {
-                        return true
-                    }
+                }
+                if visited.insert(connection.get_to()) {
+                    frontier.push(connection.get_to());
                 }
             }
         }
         false
-
-        // OPTION 1: looks like O(c^2) atleast (in my medicated state atleast) where c is connections
-        //create empty Vec of node_innovs
-        //iterate over connections
-            // If conn_from = from_node_inov
-                // if conn_to = to_node_innov
-                    // return true
-                // else 
-                    // store conn_to in Vec
-
-        //while Vec.len > 0
-            // Iterate over connections
-                //If conn_from = from_node_innov
-                    //If conn_to = to_node_innov
-                        //return true
-                    // Else 
-                        //store conn_to in Vec
-        // Return False
-
-        // OPTION 2 .. looks  like O(2N + C) n is nodes, c is conns
-
-
-        // Build directed graph of nodes from connection genes. 
-        // Similar to the NN built in feed forward, except next_nodes are stored instead of input_nodes
-            // We can then do a DFS/BFS from the from_node_innov for the to_node_innov
     }
 
     //================================GETTERS==========================//
@@ -159,30 +118,18 @@ impl Genome {
         }
     }
 
-    //ensure genome has required nodes for input, bias and output
-    #[allow(dead_code)]
-    fn has_ibo(&self) -> bool {
-        let mut res = true;
-        for i in 0..(INPUTS + BIAS as usize + OUTPUTS) {
-            if !self.nodes.contains_innov(i) {
-                res = false;
-            }
-        }
-        res
-    }
-
     #[allow(dead_code)]
     pub fn new_init_xor() -> Genome {
         let mut g = Genome {
             nodes: {
                 // Store a clone of input, bias and outputs nodes
                 let mut init_nodes = VecSet::new();
-                init_nodes.push(NodeGene::new(crate::NodeType::Input, 0, 0.0, 0.75));
-                init_nodes.push(NodeGene::new(crate::NodeType::Input, 1, 0.0, 0.5));
-                init_nodes.push(NodeGene::new(crate::NodeType::Bias, 2, 0.0, 0.1));
-                init_nodes.push(NodeGene::new(crate::NodeType::Output, 3, 1.0, 0.5));
-                init_nodes.push(NodeGene::new(crate::NodeType::Hidden, 4, 0.5, 0.75));
-                init_nodes.push(NodeGene::new(crate::NodeType::Hidden, 5, 0.5, 0.5));
+                init_nodes.push(NodeGene::new(crate::NodeType::Input, 0, 0.0, 0.75, crate::ActivationFn::Identity));
+                init_nodes.push(NodeGene::new(crate::NodeType::Input, 1, 0.0, 0.5, crate::ActivationFn::Identity));
+                init_nodes.push(NodeGene::new(crate::NodeType::Bias, 2, 0.0, 0.1, crate::ActivationFn::Identity));
+                init_nodes.push(NodeGene::new(crate::NodeType::Output, 3, 1.0, 0.5, crate::ActivationFn::Sigmoid));
+                init_nodes.push(NodeGene::new(crate::NodeType::Hidden, 4, 0.5, 0.75, crate::ActivationFn::Sigmoid));
+                init_nodes.push(NodeGene::new(crate::NodeType::Hidden, 5, 0.5, 0.5, crate::ActivationFn::Sigmoid));
                 init_nodes
             },
             connections: VecSet::new(),
@@ -192,19 +139,19 @@ impl Genome {
     }
     
     #[allow(dead_code)]
-    pub fn xor_rand_connect(&mut self) {
+    pub fn xor_rand_connect<R: Rng + ?Sized>(&mut self, config:&NeatConfig, rng:&mut R) {
         
-        self.connections.push(ConnectionGene::new(0, 0, 4));
-        self.connections.push(ConnectionGene::new(1, 0, 5));
-        self.connections.push(ConnectionGene::new(2, 1, 4));
-        self.connections.push(ConnectionGene::new(3, 1, 5));
+        self.connections.push(ConnectionGene::new(0, 0, 4, config, rng));
+        self.connections.push(ConnectionGene::new(1, 0, 5, config, rng));
+        self.connections.push(ConnectionGene::new(2, 1, 4, config, rng));
+        self.connections.push(ConnectionGene::new(3, 1, 5, config, rng));
 
-        self.connections.push(ConnectionGene::new(4, 4, 3));
-        self.connections.push(ConnectionGene::new(5, 5, 3));
+        self.connections.push(ConnectionGene::new(4, 4, 3, config, rng));
+        self.connections.push(ConnectionGene::new(5, 5, 3, config, rng));
 
-        self.connections.push(ConnectionGene::new(6, 2, 3));
-        self.connections.push(ConnectionGene::new(7, 2, 4));
-        self.connections.push(ConnectionGene::new(8, 2, 5));
+        self.connections.push(ConnectionGene::new(6, 2, 3, config, rng));
+        self.connections.push(ConnectionGene::new(7, 2, 4, config, rng));
+        self.connections.push(ConnectionGene::new(8, 2, 5, config, rng));
     }
 
     #[allow(dead_code)]
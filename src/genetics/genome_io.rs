@@ -0,0 +1,271 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use bincode::Options;
+use serde::{Serialize, Deserialize};
+
+use crate::util::{ActivationFn, AggregationFn};
+use super::{Genome, NodeGene, NodeType, ConnectionGene, Gene, GenomeViolation};
+
+// Genome files are written in the latest format version.
+// In JSON a node's activation, aggregation, bias, response & time_constant are optional, taking neutral defaults,
+// so hand-written files need only describe the topology and weights.
+const FORMAT_VERSION:u32 = 1;
+const BINARY_MAGIC:&[u8; 4] = b"NEAT";
+
+/// Returned when a genome file cannot be read or decoded.
+#[derive(Debug)]
+pub enum GenomeFormatError {
+    Io(io::Error),
+    Json(String),
+    Binary(String),
+    BadMagic, // the binary file does not start with the NEAT magic bytes
+    UnsupportedVersion(u32),
+    Invalid(GenomeViolation), // the file describes a genome which cannot be represented, such as one with duplicate innovations
+}
+
+impl fmt::Display for GenomeFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeFormatError::Io(e) => write!(f, "genome io error: {}", e),
+            GenomeFormatError::Json(e) => write!(f, "malformed genome json: {}", e),
+            GenomeFormatError::Binary(e) => write!(f, "malformed genome binary: {}", e),
+            GenomeFormatError::BadMagic => write!(f, "not a genome binary file"),
+            GenomeFormatError::UnsupportedVersion(v) => write!(f, "genome format version {} is not supported, latest is {}", v, FORMAT_VERSION),
+            GenomeFormatError::Invalid(violation) => write!(f, "invalid genome: {}", violation),
+        }
+    }
+}
+
+impl std::error::Error for GenomeFormatError {}
+
+//================================FORMAT VERSION 1==========================//
+
+#[derive(Serialize, Deserialize)]
+struct NodeRecord {
+    innovation: usize,
+    #[serde(rename = "type")]
+    node_type: NodeType,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    activation: Option<ActivationFn>, // when missing, sigmoid for hidden & output nodes, identity otherwise
+    #[serde(default)]
+    aggregation: Option<AggregationFn>, // when missing, sum
+    #[serde(default)]
+    bias: f64,
+    #[serde(default = "one")]
+    response: f64,
+    #[serde(default = "one")]
+    time_constant: f64,
+}
+
+fn one() -> f64 { 1.0 }
+
+#[derive(Serialize, Deserialize)]
+struct ConnectionRecord {
+    innovation: usize,
+    from: usize,
+    to: usize,
+    weight: f64,
+    enabled: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GenomeRecord {
+    nodes: Vec<NodeRecord>,
+    connections: Vec<ConnectionRecord>,
+}
+
+impl GenomeRecord {
+    fn from_genome(genome:&Genome) -> Self {
+        Self {
+            nodes: genome.get_nodes().iter().map(|node| NodeRecord {
+                innovation: node.get_innov(),
+                node_type: node.get_nodetype(),
+                x: node.get_x(),
+                y: node.get_y(),
+                activation: Some(node.get_activation()),
+                aggregation: Some(node.get_aggregation()),
+                bias: node.get_bias(),
+                response: node.get_response(),
+                time_constant: node.get_time_constant(),
+            }).collect(),
+            connections: genome.get_connections().iter().map(|conn| ConnectionRecord {
+                innovation: conn.get_innov(),
+                from: conn.get_from(),
+                to: conn.get_to(),
+                weight: conn.get_weight(),
+                enabled: conn.is_enabled(),
+            }).collect(),
+        }
+    }
+
+    fn into_genome(self) -> Result<Genome, GenomeFormatError> {
+        let mut genome = Genome::new();
+        for node in self.nodes {
+            let activation = node.activation.unwrap_or(match node.node_type {
+                NodeType::Input | NodeType::Bias => ActivationFn::Identity,
+                NodeType::Output | NodeType::Hidden => ActivationFn::Sigmoid,
+            });
+            let mut node_gene = NodeGene::new(node.node_type, node.innovation, node.x, node.y, activation);
+            node_gene.set_aggregation(node.aggregation.unwrap_or(AggregationFn::Sum));
+            node_gene.set_bias(node.bias);
+            node_gene.set_response(node.response);
+            node_gene.set_time_constant(node.time_constant);
+            if !genome.get_nodes_mut().insert_sorted(node_gene) {
+                return Err(GenomeFormatError::Invalid(GenomeViolation::DuplicateNode(node.innovation)))
+            }
+        }
+        for conn in self.connections {
+            if !genome.get_connections_mut().insert_sorted(ConnectionGene::new_explicit(conn.innovation, conn.weight, conn.enabled, conn.from, conn.to)) {
+                return Err(GenomeFormatError::Invalid(GenomeViolation::DuplicateConnection(conn.innovation)))
+            }
+        }
+        Ok(genome)
+    }
+}
+
+//==================================JSON=====================================//
+
+#[derive(Serialize)]
+struct JsonFileOut<'a> {
+    version: u32,
+    #[serde(flatten)]
+    genome: &'a GenomeRecord,
+}
+
+#[derive(Deserialize)]
+struct JsonVersion {
+    version: u32,
+}
+
+//=================================BINARY====================================//
+
+fn binary_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+impl Genome {
+
+    /// Encodes the genome as human-readable JSON, tagged with the format version.
+    pub fn to_json(&self) -> String {
+        let record = GenomeRecord::from_genome(self);
+        serde_json::to_string_pretty(&JsonFileOut { version: FORMAT_VERSION, genome: &record }).unwrap()
+    }
+
+    /// Decodes a genome from JSON written by Genome::to_json() of this or any earlier format version.
+    pub fn from_json(json:&str) -> Result<Genome, GenomeFormatError> {
+        let version: JsonVersion = serde_json::from_str(json).map_err(|e| GenomeFormatError::Json(e.to_string()))?;
+        match version.version {
+            1 => {
+                let record: GenomeRecord = serde_json::from_str(json).map_err(|e| GenomeFormatError::Json(e.to_string()))?;
+                record.into_genome()
+            }
+            v => Err(GenomeFormatError::UnsupportedVersion(v)),
+        }
+    }
+
+    /// Encodes the genome in a compact binary format: the magic bytes "NEAT", a little-endian u32 format version, then the genes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend(binary_options().serialize(&GenomeRecord::from_genome(self)).unwrap());
+        bytes
+    }
+
+    /// Decodes a genome from bytes written by Genome::to_bytes() of this or any earlier format version.
+    pub fn from_bytes(bytes:&[u8]) -> Result<Genome, GenomeFormatError> {
+        if bytes.len() < 8 || &bytes[0..4] != BINARY_MAGIC {
+            return Err(GenomeFormatError::BadMagic)
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let payload = &bytes[8..];
+        match version {
+            1 => {
+                let record: GenomeRecord = binary_options().deserialize(payload).map_err(|e| GenomeFormatError::Binary(e.to_string()))?;
+                record.into_genome()
+            }
+            v => Err(GenomeFormatError::UnsupportedVersion(v)),
+        }
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path:P) -> Result<(), GenomeFormatError> {
+        fs::write(path, self.to_json()).map_err(GenomeFormatError::Io)
+    }
+
+    pub fn load_json<P: AsRef<Path>>(path:P) -> Result<Genome, GenomeFormatError> {
+        Genome::from_json(&fs::read_to_string(path).map_err(GenomeFormatError::Io)?)
+    }
+
+    pub fn save_binary<P: AsRef<Path>>(&self, path:P) -> Result<(), GenomeFormatError> {
+        fs::write(path, self.to_bytes()).map_err(GenomeFormatError::Io)
+    }
+
+    pub fn load_binary<P: AsRef<Path>>(path:P) -> Result<Genome, GenomeFormatError> {
+        Genome::from_bytes(&fs::read(path).map_err(GenomeFormatError::Io)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{ActivationFn, AggregationFn};
+    use crate::genetics::GenomeViolation;
+    use super::{Genome, GenomeFormatError};
+
+    #[test]
+    fn json_roundtrip() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_connections_mut().rand_element_mut(&mut rand::thread_rng()).set_enabled(false);
+        genome.get_nodes_mut().get_by_innov_mut(4).set_bias(0.25);
+        genome.get_nodes_mut().get_by_innov_mut(5).set_aggregation(AggregationFn::Median);
+        genome.get_nodes_mut().get_by_innov_mut(3).set_time_constant(0.5);
+        let json = genome.to_json();
+        assert!(json.contains("\"version\": 1"));
+        let loaded = Genome::from_json(&json).unwrap();
+        assert_eq!(json, loaded.to_json());
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let genome = Genome::new_init_xor();
+        let bytes = genome.to_bytes();
+        assert!(bytes.len() < genome.to_json().len());
+        let loaded = Genome::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, loaded.to_bytes());
+    }
+
+    #[test]
+    fn loads_files_without_later_genes() {
+        let json = r#"{
+            "version": 1,
+            "nodes": [
+                { "innovation": 0, "type": "Input", "x": 0.0, "y": 0.5 },
+                { "innovation": 1, "type": "Output", "x": 1.0, "y": 0.5 }
+            ],
+            "connections": [
+                { "innovation": 0, "from": 0, "to": 1, "weight": 0.5, "enabled": true }
+            ]
+        }"#;
+        let genome = Genome::from_json(json).unwrap();
+        assert_eq!(genome.get_nodes().get_by_innov(1).get_activation(), ActivationFn::Sigmoid);
+        assert_eq!(genome.get_nodes().get_by_innov(1).get_response(), 1.0);
+        assert_eq!(genome.get_connections().get_by_innov(0).get_weight(), 0.5);
+    }
+
+    #[test]
+    fn rejects_duplicate_innovations() {
+        let json = Genome::new_init_xor().to_json().replacen("\"innovation\": 1,", "\"innovation\": 0,", 1);
+        assert!(matches!(Genome::from_json(&json), Err(GenomeFormatError::Invalid(GenomeViolation::DuplicateNode(0)))));
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut bytes = Genome::new_init_xor().to_bytes();
+        bytes[4] = 99;
+        assert!(matches!(Genome::from_bytes(&bytes), Err(GenomeFormatError::UnsupportedVersion(99))));
+        assert!(matches!(Genome::from_bytes(b"nope"), Err(GenomeFormatError::BadMagic)));
+        assert!(matches!(Genome::from_json("{\"version\": 42}"), Err(GenomeFormatError::UnsupportedVersion(42))));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::neural_network::FeedForwardNetwork;
+use crate::util::AggregationFn;
+use super::{Genome, ConnectionGene, NodeType, Gene};
+
+impl Genome {
+
+    /// Returns a functionally identical copy of the genome with everything which cannot affect its outputs removed:
+    /// disabled connections, hidden nodes with no path to an output, and hidden nodes with no path from an input or the bias.
+    /// Nodes unreachable from the inputs & bias always produce the same activation, so their contribution
+    /// is folded into the bias of each node they feed. No connections are added, so every innovation number keeps its meaning.
+    /// Only summed inputs can be folded, so unreachable nodes feeding a node with any other aggregation function are kept.
+    /// Input, bias & output nodes are never removed. Only for feed-forward genomes, as constants are found with a FeedForwardNetwork.
+    pub fn pruned(&self) -> Genome {
+        let enabled: Vec<&ConnectionGene> = self.get_connections().iter().filter(|conn| conn.is_enabled()).collect();
+
+        // Nodes with a path from an input or the bias
+        let roots = self.get_nodes().iter()
+            .filter(|node| matches!(node.get_nodetype(), NodeType::Input | NodeType::Bias))
+            .map(|node| node.get_innov());
+        let reachable = flood(roots, &enabled, |conn| (conn.get_from(), conn.get_to()));
+
+        // Nodes with a path to an output
+        let outputs = self.get_nodes().iter()
+            .filter(|node| node.get_nodetype() == NodeType::Output)
+            .map(|node| node.get_innov());
+        let live = flood(outputs, &enabled, |conn| (conn.get_to(), conn.get_from()));
+
+        let needed = |innov:usize| {
+            self.get_nodes().get_by_innov(innov).get_nodetype() != NodeType::Hidden
+            || (live.contains(&innov) && reachable.contains(&innov))
+        };
+
+        // Unreachable nodes feeding a node which does not sum its inputs, and every node they depend on
+        let unfoldable = enabled.iter()
+            .filter(|conn| !needed(conn.get_from()) && live.contains(&conn.get_from()) && needed(conn.get_to()))
+            .filter(|conn| self.get_nodes().get_by_innov(conn.get_to()).get_aggregation() != AggregationFn::Sum)
+            .map(|conn| conn.get_from());
+        let unfoldable = flood(unfoldable, &enabled, |conn| (conn.get_to(), conn.get_from()));
+
+        let keep = |innov:usize| needed(innov) || unfoldable.contains(&innov);
+
+        // Activations of unreachable nodes do not depend on the inputs, so any input gives their constant value
+        let mut network = FeedForwardNetwork::new(self);
+        network.activate(vec![0.0; network.num_inputs()]);
+
+        let mut pruned = Genome::new();
+        for node in self.get_nodes().iter() {
+            if keep(node.get_innov()) {
+                pruned.get_nodes_mut().insert_sorted(node.clone());
+            }
+        }
+
+        let mut folds: HashMap<usize, f64> = HashMap::new(); // to -> constant input
+        for conn in enabled.iter() {
+            let (from, to) = (conn.get_from(), conn.get_to());
+            if keep(from) && keep(to) {
+                pruned.get_connections_mut().insert_sorted((*conn).clone());
+            } else if keep(to) && live.contains(&from) {
+                *folds.entry(to).or_insert(0.0) += network.get_activation(from).unwrap() * conn.get_weight();
+            }
+        }
+
+        for (to, constant) in folds {
+            let node = pruned.get_nodes_mut().get_by_innov_mut(to);
+            node.set_bias(node.get_bias() + node.get_response() * constant);
+        }
+        pruned
+    }
+}
+
+/// Returns every node reachable from start by following connections, where step maps a connection to its (source, destination).
+fn flood<I, F>(start:I, connections:&[&ConnectionGene], step:F) -> HashSet<usize>
+where I: Iterator<Item = usize>, F: Fn(&ConnectionGene) -> (usize, usize) {
+    let mut visited: HashSet<usize> = start.collect();
+    let mut frontier: Vec<usize> = visited.iter().copied().collect();
+    while let Some(node) = frontier.pop() {
+        for conn in connections.iter() {
+            let (source, destination) = step(conn);
+            if source == node && visited.insert(destination) {
+                frontier.push(destination);
+            }
+        }
+    }
+    visited
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use crate::{Neat, NeatConfig, NeatRng, Termination, FeedForwardNetwork, ActivationFn, AggregationFn};
+    use crate::genetics::{Genome, ConnectionGene, NodeGene, NodeType, Gene};
+    use crate::test_environments::xor;
+
+    #[test]
+    fn prunes_dead_genes() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 6, 0.5, 0.3, ActivationFn::Sigmoid)); // dead end
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 7, 0.5, 0.2, ActivationFn::Sigmoid)); // unreachable
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(9, 3.0, true, 0, 6));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(10, 4.0, true, 7, 3));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(11, 5.0, false, 1, 3));
+
+        let pruned = genome.pruned();
+        assert_eq!(pruned.get_nodes().len(), 6);
+        assert_eq!(pruned.get_connections().len(), 9);
+        assert!(pruned.get_connections().iter().all(|conn| conn.is_enabled()));
+        assert!(pruned.validate(&NeatConfig::new(2, 1)).is_empty());
+
+        let (mut original, mut simplified) = (FeedForwardNetwork::new(&genome), FeedForwardNetwork::new(&pruned));
+        for inputs in [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]] {
+            let (a, b) = (original.activate(inputs.to_vec()), simplified.activate(inputs.to_vec()));
+            assert!((a[0] - b[0]).abs() < 1e-12, "{} != {}", a[0], b[0]);
+        }
+    }
+
+    #[test]
+    fn keeps_unreachable_inputs_of_products() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_nodes_mut().get_by_innov_mut(3).set_aggregation(AggregationFn::Product);
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 6, 0.5, 0.2, ActivationFn::Sigmoid));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(9, 4.0, true, 6, 3));
+
+        let pruned = genome.pruned();
+        assert!(pruned.get_nodes().contains_innov(6));
+        let (mut original, mut simplified) = (FeedForwardNetwork::new(&genome), FeedForwardNetwork::new(&pruned));
+        assert_eq!(original.activate(vec![1.0, 0.0]), simplified.activate(vec![1.0, 0.0]));
+    }
+
+    #[test]
+    fn pruned_outputs_are_identical() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.m_node = 0.3;
+        config.m_conn = 0.3;
+        config.m_conn_enabled = 0.2;
+        config.seed = Some(5);
+        let mut neat = Neat::new_fully_connected(config);
+        neat.train_until(xor, &Termination { max_generations: Some(20), ..Termination::default() });
+
+        let mut rng = NeatRng::seed_from_u64(0);
+        for genome in neat.get_population().organisms.read().unwrap().iter() {
+            let pruned = genome.pruned();
+            for conn in pruned.get_connections().iter() {
+                let original = genome.get_connections().get_by_innov(conn.get_innov());
+                assert_eq!((conn.get_from(), conn.get_to()), (original.get_from(), original.get_to()));
+            }
+            let mut original = FeedForwardNetwork::new(genome);
+            let mut simplified = FeedForwardNetwork::new(&pruned);
+            for _ in 0..20 {
+                let inputs = vec![rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)];
+                let (a, b) = (original.activate(inputs.clone()), simplified.activate(inputs));
+                assert!((a[0] - b[0]).abs() < 1e-12, "{} != {}", a[0], b[0]);
+            }
+        }
+    }
+}
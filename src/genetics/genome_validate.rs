@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::NeatConfig;
+use super::{Genome, Gene};
+
+/// A broken invariant found by Genome::validate().
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenomeViolation {
+    MissingNode(usize), // innovation number of a required input, bias or output node
+    DanglingConnection { innov:usize, node:usize }, // the connection references a node the genome lacks
+    UnsortedNodes(usize), // innovation number of the first node gene out of order
+    UnsortedConnections(usize), // innovation number of the first connection gene out of order
+    DuplicateNode(usize), // only found when loading, as a VecSet cannot hold duplicate innovations
+    DuplicateConnection(usize), // only found when loading, as a VecSet cannot hold duplicate innovations
+    Cycle(Vec<usize>), // node innovation numbers around the cycle, in connection order
+    BackwardConnection { innov:usize, from:usize, to:usize }, // the 'from' node is not left of the 'to' node
+}
+
+impl fmt::Display for GenomeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeViolation::MissingNode(innov) => write!(f, "required node {} is missing", innov),
+            GenomeViolation::DanglingConnection { innov, node } => write!(f, "connection {} references missing node {}", innov, node),
+            GenomeViolation::UnsortedNodes(innov) => write!(f, "node genes are unsorted at node {}", innov),
+            GenomeViolation::UnsortedConnections(innov) => write!(f, "connection genes are unsorted at connection {}", innov),
+            GenomeViolation::DuplicateNode(innov) => write!(f, "node {} appears more than once", innov),
+            GenomeViolation::DuplicateConnection(innov) => write!(f, "connection {} appears more than once", innov),
+            GenomeViolation::Cycle(nodes) => {
+                let nodes: Vec<String> = nodes.iter().map(|node| node.to_string()).collect();
+                write!(f, "cycle through nodes {}", nodes.join(" -> "))
+            }
+            GenomeViolation::BackwardConnection { innov, from, to } => write!(f, "connection {} runs backwards from node {} to node {}", innov, from, to),
+        }
+    }
+}
+
+// DFS state of each node while searching for cycles
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+impl Genome {
+
+    /// Checks the genome is well formed, returning every violation found. An empty Vec means the genome is valid.
+    /// Checks for missing input, bias & output nodes, connections to absent nodes, unsorted innovations,
+    /// cycles, and connections which do not run from left to right. Cycles & backward connections are allowed when config.recurrent is set.
+    pub fn validate(&self, config:&NeatConfig) -> Vec<GenomeViolation> {
+        let mut violations = Vec::new();
+
+        for i in 0..config.num_ibo() {
+            if !self.get_nodes().contains_innov(i) {
+                violations.push(GenomeViolation::MissingNode(i));
+            }
+        }
+
+        let node_innovs: Vec<usize> = self.get_nodes().iter().map(|node| node.get_innov()).collect();
+        for pair in node_innovs.windows(2) {
+            if pair[1] < pair[0] {
+                violations.push(GenomeViolation::UnsortedNodes(pair[1]));
+            }
+        }
+        let conn_innovs: Vec<usize> = self.get_connections().iter().map(|conn| conn.get_innov()).collect();
+        for pair in conn_innovs.windows(2) {
+            if pair[1] < pair[0] {
+                violations.push(GenomeViolation::UnsortedConnections(pair[1]));
+            }
+        }
+
+        // Connections between present nodes, as an adjacency list. Both enabled and disabled connections count,
+        // as a disabled connection may be re-enabled by mutation.
+        let x_coords: HashMap<usize, f64> = self.get_nodes().iter().map(|node| (node.get_innov(), node.get_x())).collect();
+        let mut next_nodes: HashMap<usize, Vec<usize>> = HashMap::new();
+        for conn in self.get_connections().iter() {
+            let (from, to) = (conn.get_from(), conn.get_to());
+            let mut dangling = false;
+            for node in [from, to] {
+                if !x_coords.contains_key(&node) {
+                    violations.push(GenomeViolation::DanglingConnection { innov: conn.get_innov(), node });
+                    dangling = true;
+                }
+            }
+            if dangling {
+                continue
+            }
+            if !config.recurrent && x_coords[&from] >= x_coords[&to] {
+                violations.push(GenomeViolation::BackwardConnection { innov: conn.get_innov(), from, to });
+            }
+            next_nodes.entry(from).or_default().push(to);
+        }
+
+        if !config.recurrent {
+            let mut visits: HashMap<usize, Visit> = node_innovs.iter().map(|innov| (*innov, Visit::Unvisited)).collect();
+            let mut path = Vec::new();
+            for innov in node_innovs.iter() {
+                if visits[innov] == Visit::Unvisited {
+                    find_cycles(*innov, &next_nodes, &mut visits, &mut path, &mut violations);
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Depth first search from node, recording a violation for each back edge found.
+/// Path holds the nodes currently being explored, so the cycle can be read off it.
+fn find_cycles(node:usize, next_nodes:&HashMap<usize, Vec<usize>>, visits:&mut HashMap<usize, Visit>, path:&mut Vec<usize>, violations:&mut Vec<GenomeViolation>) {
+    visits.insert(node, Visit::InProgress);
+    path.push(node);
+    if let Some(nexts) = next_nodes.get(&node) {
+        for next in nexts.iter() {
+            match visits[next] {
+                Visit::Unvisited => find_cycles(*next, next_nodes, visits, path, violations),
+                Visit::InProgress => {
+                    let start = path.iter().position(|n| n == next).unwrap();
+                    violations.push(GenomeViolation::Cycle(path[start..].to_vec()));
+                }
+                Visit::Done => (),
+            }
+        }
+    }
+    path.pop();
+    visits.insert(node, Visit::Done);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Neat, NeatConfig, Termination, ActivationFn};
+    use crate::genetics::{Genome, ConnectionGene, NodeGene, NodeType};
+    use crate::test_environments::xor;
+    use super::GenomeViolation;
+
+    #[test]
+    fn xor_is_valid() {
+        assert!(Genome::new_init_xor().validate(&NeatConfig::new(2, 1)).is_empty());
+    }
+
+    #[test]
+    fn finds_violations() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_nodes_mut().remove(1);
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 6, 0.5, 0.1, ActivationFn::Sigmoid));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(9, 1.0, false, 3, 4));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(10, 1.0, true, 6, 6));
+        let violations = genome.validate(&NeatConfig::new(2, 1));
+
+        assert!(violations.contains(&GenomeViolation::MissingNode(1)));
+        assert!(violations.contains(&GenomeViolation::DanglingConnection { innov: 2, node: 1 }));
+        assert!(violations.contains(&GenomeViolation::BackwardConnection { innov: 9, from: 3, to: 4 }));
+        assert!(violations.contains(&GenomeViolation::Cycle(vec![4, 3])));
+        assert!(violations.contains(&GenomeViolation::Cycle(vec![6])));
+    }
+
+    #[test]
+    fn finds_unsorted_innovations() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(20, 1.0, true, 0, 3));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(12, 1.0, true, 1, 3));
+        assert_eq!(genome.validate(&NeatConfig::new(2, 1)), vec![GenomeViolation::UnsortedConnections(12)]);
+    }
+
+    #[test]
+    fn evolved_genomes_are_valid() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.m_node = 0.2;
+        config.m_conn = 0.3;
+        config.validate_genomes = true;
+        Neat::new_fully_connected(config).train_until(xor, &Termination { max_generations: Some(20), ..Termination::default() });
+    }
+}
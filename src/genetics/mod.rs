@@ -1,10 +1,15 @@
 mod genome;
+mod genome_io;
+mod genome_validate;
+mod genome_prune;
 mod connection_gene;
 mod node_gene;
 mod gene;
 mod util;
 
 pub use genome::Genome;
+pub use genome_io::GenomeFormatError;
+pub use genome_validate::GenomeViolation;
 pub use node_gene::{NodeGene, NodeType};
 pub use connection_gene::ConnectionGene;
 pub use gene::Gene;
@@ -1,8 +1,11 @@
+use serde::{Serialize, Deserialize};
+
+use crate::util::{ActivationFn, AggregationFn};
 use super::gene::Gene;
 use std::collections::HashMap;
 
 // Specifies whether a node is an input, bias, output or hidden node.
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum NodeType {
     Input,
     Bias,
@@ -12,12 +15,17 @@ pub enum NodeType {
 
 
 /// Encoding for a node in a neural network
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NodeGene {
     node_type: NodeType,
     innovation_number: usize,
     x: f64,
     y: f64,
+    activation: ActivationFn, // unused by input & bias nodes, whose activation is set directly
+    aggregation: AggregationFn, // combines the weighted inputs. Unused by input & bias nodes
+    bias: f64, // added to the node's weighted input sum. Only evolved when config.node_parameters is set
+    response: f64, // multiplies the node's weighted input sum. Only evolved when config.node_parameters is set
+    time_constant: f64, // how quickly the node responds to its inputs in a Ctrnn. Ignored by other networks
 }
 
 impl Gene for NodeGene {
@@ -27,14 +35,18 @@ impl Gene for NodeGene {
 
 impl NodeGene {
 
-    /// Constructs a new NodeGene.
-    /// Must be fully specified 
-    pub fn new(node_type:NodeType, innovation_number:usize, x:f64, y:f64) -> NodeGene {
+    /// Constructs a new NodeGene which sums its inputs, with a bias of 0 and a response of 1, which leave the sum unchanged, and a time constant of 1.
+    pub fn new(node_type:NodeType, innovation_number:usize, x:f64, y:f64, activation:ActivationFn) -> NodeGene {
         NodeGene {
             node_type,
             innovation_number,
             x,
             y, 
+            activation,
+            aggregation: AggregationFn::Sum,
+            bias: 0.0,
+            response: 1.0,
+            time_constant: 1.0,
         }
     }
 
@@ -43,11 +55,23 @@ impl NodeGene {
     pub fn get_nodetype(&self) -> NodeType { self.node_type }
     pub fn get_x(&self) -> f64 { self.x }
     pub fn get_y(&self) -> f64 { self.y }
+    pub fn get_activation(&self) -> ActivationFn { self.activation }
+    pub fn get_aggregation(&self) -> AggregationFn { self.aggregation }
+    pub fn get_bias(&self) -> f64 { self.bias }
+    pub fn get_response(&self) -> f64 { self.response }
+    pub fn get_time_constant(&self) -> f64 { self.time_constant }
+
+    pub fn set_innov(&mut self, innovation_number:usize) { self.innovation_number = innovation_number; }
+    pub fn set_activation(&mut self, activation:ActivationFn) { self.activation = activation; }
+    pub fn set_aggregation(&mut self, aggregation:AggregationFn) { self.aggregation = aggregation; }
+    pub fn set_bias(&mut self, bias:f64) { self.bias = bias; }
+    pub fn set_response(&mut self, response:f64) { self.response = response; }
+    pub fn set_time_constant(&mut self, time_constant:f64) { self.time_constant = time_constant; }
 
     //=============================DEBUGGING===============================//
 
     pub fn print(&self) {
-        println!("{:?} Node: {}, x : {}, y: {} ", self.get_nodetype(), self.get_innov(), self.get_x(), self.get_y());
+        println!("{:?} Node: {}, x : {}, y: {}, activation: {}, aggregation: {}, bias: {}, response: {}, time constant: {} ", self.get_nodetype(), self.get_innov(), self.get_x(), self.get_y(), self.get_activation(), self.get_aggregation(), self.get_bias(), self.get_response(), self.get_time_constant());
     }
     #[allow(dead_code)]
     pub fn xor_node_gene_pool() -> HashMap<usize, NodeGene> {
@@ -55,12 +79,12 @@ impl NodeGene {
         let mut node_gene_pool:HashMap<usize, NodeGene> = HashMap::new();
 
         // Insert input, bias & output nodes into pool, required to create population
-        node_gene_pool.insert(0, NodeGene::new(NodeType::Input, 0, 0.0, 0.75));
-        node_gene_pool.insert(1, NodeGene::new(NodeType::Input, 1, 0.0, 0.5));
-        node_gene_pool.insert(2, NodeGene::new(NodeType::Bias, 2, 0.0, 0.1));
-        node_gene_pool.insert(3, NodeGene::new(NodeType::Output, 3, 1.0, 0.5));
-        node_gene_pool.insert(4, NodeGene::new(NodeType::Hidden, 4, 0.5, 0.75));
-        node_gene_pool.insert(5, NodeGene::new(NodeType::Hidden, 5, 0.5, 0.5));
+        node_gene_pool.insert(0, NodeGene::new(NodeType::Input, 0, 0.0, 0.75, ActivationFn::Identity));
+        node_gene_pool.insert(1, NodeGene::new(NodeType::Input, 1, 0.0, 0.5, ActivationFn::Identity));
+        node_gene_pool.insert(2, NodeGene::new(NodeType::Bias, 2, 0.0, 0.1, ActivationFn::Identity));
+        node_gene_pool.insert(3, NodeGene::new(NodeType::Output, 3, 1.0, 0.5, ActivationFn::Sigmoid));
+        node_gene_pool.insert(4, NodeGene::new(NodeType::Hidden, 4, 0.5, 0.75, ActivationFn::Sigmoid));
+        node_gene_pool.insert(5, NodeGene::new(NodeType::Hidden, 5, 0.5, 0.5, ActivationFn::Sigmoid));
 
         node_gene_pool
     }
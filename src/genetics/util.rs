@@ -1,11 +1,11 @@
 use super::{Genome, Gene};
-use crate::config::{C1, C2, C3, MAX_NODES};
+use crate::config::{NeatConfig, MAX_NODES};
 use rand::prelude::*;
 
 
-/// Returns a value for the similarity between two genomes based on their nodes, connections and weights.
+/// Returns a value for the similarity between two genomes based on their nodes, connections, weights, activation & aggregation functions, biases, responses & time constants.
 /// This value is used in determining which species a genome belongs to.
-pub fn distance(g1:&Genome, g2:&Genome) -> f64 {
+pub fn distance(g1:&Genome, g2:&Genome, config:&NeatConfig) -> f64 {
 
     let mut num_disjoint:usize = 0;
     let mut num_excess:usize = 0;
@@ -81,21 +81,48 @@ pub fn distance(g1:&Genome, g2:&Genome) -> f64 {
         max_num_enabled = 1;
     }
 
-    (C1 * num_excess as f64 / max_num_enabled as f64) 
+    // Average number of differing activation & aggregation functions among nodes present in both genomes,
+    // and the average difference in their biases, responses & time constants, which is weighted like connection weights.
+    let mut num_matching_nodes:usize = 0;
+    let mut num_function_mismatches:usize = 0;
+    let mut avg_node_param_diff:f64 = 0.0;
+    for node in g1.get_nodes().iter() {
+        if g2.get_nodes().contains_innov(node.get_innov()) {
+            let other = g2.get_nodes().get_by_innov(node.get_innov());
+            num_matching_nodes += 1;
+            if other.get_activation() != node.get_activation() {
+                num_function_mismatches += 1;
+            }
+            if other.get_aggregation() != node.get_aggregation() {
+                num_function_mismatches += 1;
+            }
+            avg_node_param_diff += (other.get_bias() - node.get_bias()).abs()
+                + (other.get_response() - node.get_response()).abs()
+                + (other.get_time_constant() - node.get_time_constant()).abs();
+        }
+    }
+    let mut function_diff = 0.0;
+    if num_matching_nodes > 0 {
+        function_diff = num_function_mismatches as f64 / num_matching_nodes as f64;
+        avg_node_param_diff /= num_matching_nodes as f64;
+    }
+
+    (config.c1 * num_excess as f64 / max_num_enabled as f64) 
     +
-    (C2 * num_disjoint as f64 / max_num_enabled as f64)
+    (config.c2 * num_disjoint as f64 / max_num_enabled as f64)
     + 
-    (C3 * avg_weight_diff)
+    (config.c3 * (avg_weight_diff + avg_node_param_diff))
+    +
+    (config.c4 * function_diff)
 }
 
 /// Creates a new Genome from two parent genomes.
 /// Uses a zipper-like method of selecting genes from parents. 
-pub fn crossover(g1:&Genome, g2:&Genome, mut g1_fitter_than_g2: bool) -> Genome {
+pub fn crossover<R: Rng + ?Sized>(g1:&Genome, g2:&Genome, mut g1_fitter_than_g2: bool, rng:&mut R) -> Genome {
     
     let mut g1_iter = g1.get_connections().iter();
     let mut g2_iter = g2.get_connections().iter();
     let mut child = Genome::new();
-    let mut rng = rand::thread_rng();
 
     if g1_fitter_than_g2 {
         *child.get_nodes_mut() = g1.get_nodes().clone();
@@ -103,6 +130,14 @@ pub fn crossover(g1:&Genome, g2:&Genome, mut g1_fitter_than_g2: bool) -> Genome
         *child.get_nodes_mut() = g2.get_nodes().clone();
     }
 
+    // Nodes present in both parents inherit their activation & aggregation functions, bias, response & time constant from either at random
+    let other_parent = if g1_fitter_than_g2 { g2 } else { g1 };
+    for node in other_parent.get_nodes().iter() {
+        if child.get_nodes().contains_innov(node.get_innov()) && rng.gen::<bool>() {
+            *child.get_nodes_mut().get_by_innov_mut(node.get_innov()) = node.clone();
+        }
+    }
+
     let child_connections = child.get_connections_mut();
 
     // Ensure g1 has the larger innovation number
@@ -158,7 +193,7 @@ pub fn conn_hashcode(from_node_inno:usize, to_node_inno:usize) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Neat, test_environments::xor};
+    use crate::{Neat, NeatConfig, ActivationFn, test_environments::xor};
 
     use super::super::node_gene::{NodeGene, NodeType};
     use super::super::connection_gene::ConnectionGene;
@@ -167,14 +202,15 @@ mod tests {
 
     #[test] 
     fn crossover_xor() {
-        let neat = Neat::new();
+        let config = NeatConfig::new(2, 1);
+        let neat = Neat::new(config.clone());
 
         // Complete XOR genome
         let genome1 = Genome::new_init_xor();
 
         // Incomplete XOR genome
-        let mut genome2 = Genome::new_init(neat.get_node_pool());
-        genome2.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 4, 0.5, 0.75));
+        let mut genome2 = Genome::new_init(neat.get_node_pool(), &config);
+        genome2.get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 4, 0.5, 0.75, ActivationFn::Sigmoid));
 
         //Layer 0
         genome2.get_connections_mut().push(ConnectionGene::new_explicit(0, 20.0, true, 0, 4));
@@ -186,7 +222,7 @@ mod tests {
         //Biases
         genome2.get_connections_mut().push(ConnectionGene::new_explicit(6, 10.0, true, 2, 4));
 
-        let child = crossover(&genome1, &genome2, true);
+        let child = crossover(&genome1, &genome2, true, &mut rand::thread_rng());
         assert!(xor(&child) > 15.9);
     }
 }
\ No newline at end of file
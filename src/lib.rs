@@ -5,106 +5,166 @@ mod util;
 mod speciation;
 mod mutation;
 mod population;
+mod visualisation;
+mod report;
+mod checkpoint;
+mod reporting;
+mod termination;
 pub mod test_environments;
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
+use serde::{Serialize, Deserialize};
+use rand::{Rng, SeedableRng};
 
-pub use genetics::{Genome, ConnectionGene, conn_hashcode, NodeGene, NodeType};
+pub use genetics::{Genome, ConnectionGene, conn_hashcode, NodeGene, NodeType, GenomeFormatError, GenomeViolation};
 pub use speciation::{Species};
-pub use neural_network::FeedForwardNetwork;
-pub use config::{INPUTS, BIAS, OUTPUTS};
-pub use population::Population;
+pub use neural_network::{FeedForwardNetwork, CompiledNetwork, RecurrentNetwork, Ctrnn};
+pub use config::{NeatConfig, ConfigError};
+pub use population::{Population, FitnessFn, Evaluator, EvalFailure, FailureReason, WorkerPool, run_worker, is_worker, WORKER_ADDR_VAR, WORKER_TOKEN_VAR};
+use population::PopulationSnapshot;
 pub use mutation::{mutate};
-pub use util::softmax;
+pub use report::{GenerationReport, SpeciesReport, PhaseTimings};
+pub use reporting::{Reporter, SilentReporter, StdoutReporter, FileReporter};
+pub use checkpoint::CheckpointError;
+pub use termination::{Termination, StopReason, TrainingResult, Progress};
+pub use util::{ActivationFn, AggregationFn, argmax, softmax, NeatRng, with_eval_rng};
 
+#[derive(Serialize, Deserialize)]
 pub struct Neat {
-    node_pool: HashMap<usize, NodeGene>, // hashcode -> NodeGene, hashcode is (INPUTS + BIAS as usize + OUTPUTS) + innov_num of the connection consumed
+    config: NeatConfig,
+    node_pool: HashMap<usize, NodeGene>, // hashcode -> NodeGene, hashcode is (inputs + bias as usize + outputs) + innov_num of the connection consumed
     connection_pool: HashMap<usize, ConnectionGene>, // hashcode -> ConnectionGene, hashcode is (from_node_innov * MAX_NODES + to_node_innov)
     population: Population,
+    started: bool, // whether the initial population has been evaluated
+    #[serde(skip)]
+    reporters: Vec<Box<dyn Reporter>>,
+    checkpointing: Option<(usize, PathBuf)>, // save a checkpoint to this path every this many generations
+    rng: NeatRng, // drives every random decision, seeded by config.seed
+}
+
+/// Everything a generation changes before it is evaluated.
+struct GenerationSnapshot {
+    population: PopulationSnapshot,
+    node_pool: HashMap<usize, NodeGene>,
+    connection_pool: HashMap<usize, ConnectionGene>,
+    rng: NeatRng,
+}
+
+/// Undoes a generation when dropped before it has been evaluated, so a cancelled step_async leaves Neat unchanged.
+struct CancelGuard<'a> {
+    neat: &'a mut Neat,
+    snapshot: Option<GenerationSnapshot>, // taken once evaluation completes
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.neat.population.restore(snapshot.population);
+            self.neat.node_pool = snapshot.node_pool;
+            self.neat.connection_pool = snapshot.connection_pool;
+            self.neat.rng = snapshot.rng;
+        }
+    }
 }
 
 impl Neat {
-    pub fn new() -> Self {
-        let new_node_pool =  {
-            let mut hashmap:HashMap<usize, NodeGene> = HashMap::new();
-            for i in 0..INPUTS {
-                let input_node_gene = NodeGene::new(
-                    NodeType::Input, 
-                    i, 
-                    0.0, 
-                    1.0 - ((i + 1) as f64 / (INPUTS + BIAS as usize + 1) as f64));
-                hashmap.insert(i, input_node_gene);
-            }
-            if BIAS {
-                let bias_node_gene = NodeGene::new(NodeType::Bias, 
-                    INPUTS, 
-                    0.0, 
-                    1.0 - ((INPUTS + 1) as f64 / (INPUTS + BIAS as usize + 1) as f64 ));
-                hashmap.insert(INPUTS, bias_node_gene);
-            }
-            for i in 0..OUTPUTS {
-                let output_node_gene = NodeGene::new(
-                    NodeType::Output, 
-                    INPUTS + BIAS as usize + i, 
-                    1.0, 
-                    1.0 - ((i + 1) as f64 / (OUTPUTS + 1) as f64));
-                hashmap.insert(INPUTS + BIAS as usize + i, output_node_gene);
-            }
-            hashmap
-        };
+    /// Panics if config is invalid, listing every problem. Check it first with NeatConfig::validate() to handle the error instead.
+    pub fn new(mut config:NeatConfig) -> Self {
+        let rng = Self::init_rng(&mut config);
+        let new_node_pool = Self::init_node_pool(&config);
         Self {
-            node_pool: new_node_pool.clone(),
+            population: Population::new(&new_node_pool, &config),
+            node_pool: new_node_pool,
             connection_pool: HashMap::new(),
-            population: Population::new(&new_node_pool),
-        }
-    }
-    pub fn new_fully_connected() -> Self {
-        let new_node_pool =  {
-            let mut hashmap:HashMap<usize, NodeGene> = HashMap::new();
-            for i in 0..INPUTS {
-                let input_node_gene = NodeGene::new(
-                    NodeType::Input, 
-                    i, 
-                    0.0, 
-                    1.0 - ((i + 1) as f64 / (INPUTS + BIAS as usize + 1) as f64));
-                hashmap.insert(i, input_node_gene);
-            }
-            if BIAS {
-                let bias_node_gene = NodeGene::new(NodeType::Bias, 
-                    INPUTS, 
-                    0.0, 
-                    1.0 - ((INPUTS + 1) as f64 / (INPUTS + BIAS as usize + 1) as f64 ));
-                hashmap.insert(INPUTS, bias_node_gene);
-            }
-            for i in 0..OUTPUTS {
-                let output_node_gene = NodeGene::new(
-                    NodeType::Output, 
-                    INPUTS + BIAS as usize + i, 
-                    1.0, 
-                    1.0 - ((i + 1) as f64 / (OUTPUTS + 1) as f64));
-                hashmap.insert(INPUTS + BIAS as usize + i, output_node_gene);
-            }
-            hashmap
-        };
+            config,
+            started: false,
+            reporters: Vec::new(),
+            checkpointing: None,
+            rng,
+        }
+    }
+    /// Panics if config is invalid, in the same way as Neat::new.
+    pub fn new_fully_connected(mut config:NeatConfig) -> Self {
+        let mut rng = Self::init_rng(&mut config);
+        let new_node_pool = Self::init_node_pool(&config);
         let new_conn_pool = {
             let mut hashmap:HashMap<usize, ConnectionGene> = HashMap::new();
             let mut conn_inno = 0;
-            for from_inno in 0..(INPUTS + BIAS as usize) {
-                for to_inno in (INPUTS + BIAS as usize)..(INPUTS + BIAS as usize + OUTPUTS) {
-                    hashmap.insert(conn_hashcode(from_inno, to_inno), ConnectionGene::new(conn_inno, from_inno, to_inno));
+            for from_inno in 0..(config.inputs + config.bias as usize) {
+                for to_inno in (config.inputs + config.bias as usize)..config.num_ibo() {
+                    hashmap.insert(conn_hashcode(from_inno, to_inno), ConnectionGene::new(conn_inno, from_inno, to_inno, &config, &mut rng));
                     conn_inno += 1;
                 }
             }
             hashmap
         };
         Self {
-            node_pool: new_node_pool.clone(),
-            connection_pool: new_conn_pool.clone(),
-            population: Population::new_fully_connected(&new_node_pool, &new_conn_pool),
+            population: Population::new_fully_connected(&new_node_pool, &new_conn_pool, &config),
+            node_pool: new_node_pool,
+            connection_pool: new_conn_pool,
+            config,
+            started: false,
+            reporters: Vec::new(),
+            checkpointing: None,
+            rng,
         }
     }
 
+    /// Constructs a Neat whose hyperparameters are read from a TOML config file.
+    /// Returns an error listing every problem if the file is unreadable or invalid.
+    /// For a fully connected population use Neat::new_fully_connected(NeatConfig::from_file(path)?)
+    pub fn from_config_file<P: AsRef<Path>>(path:P) -> Result<Self, ConfigError> {
+        Ok(Self::new(NeatConfig::from_file(path)?))
+    }
+
+    /// Creates the rng seeded by config.seed, after checking config is valid.
+    /// If no seed is set one is chosen at random and stored in config, so the run can be replayed.
+    fn init_rng(config:&mut NeatConfig) -> NeatRng {
+        if let Err(e) = config.validate() {
+            panic!("{}", e);
+        }
+        let seed = *config.seed.get_or_insert_with(|| rand::thread_rng().gen());
+        NeatRng::seed_from_u64(seed)
+    }
+
+    /// Builds the node_pool containing only the input, bias & output nodes described by config.
+    fn init_node_pool(config:&NeatConfig) -> HashMap<usize, NodeGene> {
+        let inputs = config.inputs;
+        let bias = config.bias as usize;
+        let mut hashmap:HashMap<usize, NodeGene> = HashMap::new();
+        for i in 0..inputs {
+            let input_node_gene = NodeGene::new(
+                NodeType::Input, 
+                i, 
+                0.0, 
+                1.0 - ((i + 1) as f64 / (inputs + bias + 1) as f64),
+                ActivationFn::Identity);
+            hashmap.insert(i, input_node_gene);
+        }
+        if config.bias {
+            let bias_node_gene = NodeGene::new(NodeType::Bias, 
+                inputs, 
+                0.0, 
+                1.0 - ((inputs + 1) as f64 / (inputs + bias + 1) as f64 ),
+                ActivationFn::Identity);
+            hashmap.insert(inputs, bias_node_gene);
+        }
+        for i in 0..config.outputs {
+            let mut output_node_gene = NodeGene::new(
+                NodeType::Output, 
+                inputs + bias + i, 
+                1.0, 
+                1.0 - ((i + 1) as f64 / (config.outputs + 1) as f64),
+                config.output_activation);
+            output_node_gene.set_aggregation(config.output_aggregation);
+            hashmap.insert(inputs + bias + i, output_node_gene);
+        }
+        hashmap
+    }
+
     // // Returns best fitness of new generation
     // pub fn run_generation(&mut self) -> f64 { 
     //     self.population.run_generation(xor)
@@ -124,41 +184,213 @@ impl Neat {
         println!("ConnPool\n{:?}", self.get_connection_pool());
     }
 
-    pub fn train(&mut self, env:for<'r> fn(&'r Genome) -> f64, target_fitness:f64) -> Genome {
-        self.population.calculate_fitnesses(env);
-        let mut best_fitness = self.population.get_max_fitness();
-        self.population.speciate();
-        self.population.generation_info();
+    /// Runs a single generation and returns a report of it.
+    /// The first call evaluates & speciates the initial population.
+    /// Each following call reproduces, mutates, evaluates & speciates a new generation.
+    pub fn step(&mut self, env:&FitnessFn) -> GenerationReport {
+        let mut timings = self.start_generation();
+
+        let now = Instant::now();
+        self.population.calculate_fitnesses(env, &mut self.rng);
+        timings.evaluate = now.elapsed();
+
+        self.finish_generation(timings)
+    }
+
+    /// Same as step(), but with an async fitness function, up to config.max_concurrent_evals of which are awaited at once.
+    /// Works with any async runtime, as evaluations are polled by the returned future rather than spawned.
+    /// Reproduction, mutation & speciation still run synchronously within the future, blocking the executor thread while they do,
+    /// so on a multi-threaded runtime prefer a dedicated thread (e.g. tokio's spawn_blocking with block_on) for large populations.
+    /// Dropping the future before it completes undoes the generation, although reporters will have seen it start.
+    pub async fn step_async<F>(&mut self, env:&F) -> GenerationReport where F: AsyncFn(&Genome) -> f64 {
+        let snapshot = GenerationSnapshot {
+            population: self.population.snapshot(),
+            node_pool: self.node_pool.clone(),
+            connection_pool: self.connection_pool.clone(),
+            rng: self.rng.clone(),
+        };
+        let mut guard = CancelGuard { neat: self, snapshot: Some(snapshot) };
+        let mut timings = guard.neat.start_generation();
+
+        let now = Instant::now();
+        guard.neat.population.calculate_fitnesses_async(env, &mut guard.neat.rng).await;
+        timings.evaluate = now.elapsed();
 
-        while best_fitness <= target_fitness {
+        guard.snapshot = None;
+        guard.neat.finish_generation(timings)
+    }
+
+    /// Reproduces & mutates a new generation, unless the initial population has not been evaluated yet.
+    fn start_generation(&mut self) -> PhaseTimings {
+        let mut timings = PhaseTimings::default();
+        let generation = if self.started { self.population.get_generation() + 1 } else { 0 };
+        for reporter in self.reporters.iter_mut() {
+            reporter.generation_start(generation);
+        }
 
-            let mut now = Instant::now();
-            self.population.reproduce();
-            // println!("reproduce() took {:.2?}", now.elapsed());
+        if self.started {
+            let now = Instant::now();
+            self.population.reproduce(&mut self.rng);
+            timings.reproduce = now.elapsed();
+            self.validate_genomes("reproduction");
 
-            now = Instant::now();
+            let now = Instant::now();
             mutate(self);
-            // println!("mutate() took {:.2?}", now.elapsed());
+            timings.mutate = now.elapsed();
+            self.validate_genomes("mutation");
+        } else {
+            self.validate_genomes("initialisation");
+        }
+        timings
+    }
+
+    /// Speciates the evaluated generation, then reports & checkpoints it.
+    fn finish_generation(&mut self, mut timings:PhaseTimings) -> GenerationReport {
+        let now = Instant::now();
+        self.population.speciate();
+        timings.speciate = now.elapsed();
+
+        self.started = true;
+        let report = GenerationReport::new(&self.population, timings);
+        self.report(&report);
+
+        if let Some((every, path)) = &self.checkpointing {
+            if report.generation.is_multiple_of(*every) {
+                if let Err(e) = self.save_checkpoint(path) {
+                    for reporter in self.reporters.iter_mut() {
+                        reporter.checkpoint_failed(report.generation, &e);
+                    }
+                }
+            }
+        }
+
+        report
+    }
+
+    /// When config.validate_genomes is set, checks every genome is well formed, panicking with the violations of the first invalid genome.
+    /// Evaluation and speciation never modify genomes, so only initialisation, reproduction & mutation are checked.
+    fn validate_genomes(&self, phase:&str) {
+        if !self.config.validate_genomes {
+            return
+        }
+        for (g_id, genome) in self.population.organisms.read().unwrap().iter().enumerate() {
+            let violations = genome.validate(&self.config);
+            if !violations.is_empty() {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                panic!("genome {} is invalid after {}: {}", g_id, phase, violations.join(", "));
+            }
+        }
+    }
+
+    /// Informs every reporter of the events in a generation.
+    fn report(&mut self, report:&GenerationReport) {
+        if self.reporters.is_empty() {
+            return
+        }
+        let champion = self.population.get_champion();
+        for reporter in self.reporters.iter_mut() {
+            if report.new_champion {
+                if let Some(champion) = &champion {
+                    reporter.new_champion(report.generation, champion, report.champion_fitness);
+                }
+            }
+            for species_id in report.created_species.iter() {
+                reporter.species_created(report.generation, *species_id);
+            }
+            for species_id in report.extinct_species.iter() {
+                reporter.species_extinct(report.generation, *species_id);
+            }
+            for species_id in report.culled_species.iter() {
+                reporter.species_culled(report.generation, *species_id);
+            }
+            for failure in report.failed_genomes.iter() {
+                reporter.genome_failed(report.generation, failure);
+            }
+            reporter.generation_end(report);
+        }
+    }
 
-            now = Instant::now();
-            self.population.calculate_fitnesses(env);
-            // println!("calc_fit() took {:.2?}", now.elapsed());
+    /// Registers a reporter to be informed of training progress. Any number of reporters can be registered.
+    /// No reporters are registered by default, so training is silent.
+    pub fn add_reporter<R: Reporter + 'static>(&mut self, reporter:R) {
+        self.reporters.push(Box::new(reporter));
+    }
 
-            now = Instant::now();
-            self.population.speciate();
-            // println!("speciate() took {:.2?}", now.elapsed());
+    pub fn clear_reporters(&mut self) {
+        self.reporters.clear();
+    }
+
+    /// Saves a checkpoint to path after every this many generations, overwriting the previous checkpoint.
+    /// Applies to both step() and train(). Training carries on if a checkpoint cannot be saved, telling the reporters instead.
+    pub fn checkpoint_every<P: Into<PathBuf>>(&mut self, generations:usize, path:P) {
+        assert!(generations > 0, "checkpoint interval must be at least 1 generation");
+        self.checkpointing = Some((generations, path.into()));
+    }
 
-            //self.population.print_species_rep();
+    /// Evolves the population until a genome's fitness exceeds target_fitness, then returns the champion.
+    /// env can be any function or closure which is safe to share between the evaluation threads.
+    pub fn train<F>(&mut self, env:F, target_fitness:f64) -> Genome 
+    where F: Fn(&Genome) -> f64 + Send + Sync + 'static
+    {
+        self.train_until(env, &Termination::target_fitness(target_fitness)).champion
+    }
 
-            self.population.generation_info();
+    /// Evolves the population until any of the termination conditions are met.
+    /// Returns the champion along with the reason training stopped.
+    pub fn train_until<F>(&mut self, env:F, termination:&Termination) -> TrainingResult 
+    where F: Fn(&Genome) -> f64 + Send + Sync + 'static
+    {
+        let env: FitnessFn = Arc::new(env);
+        let (start, start_evaluations) = (Instant::now(), self.population.get_evaluations());
+        let mut generations = 0;
+        loop {
+            let report = self.step(&env);
+            generations += 1;
+            let progress = Progress { generations, evaluations: report.evaluations - start_evaluations, elapsed: start.elapsed() };
+            if let Some(reason) = termination.check(&report, &progress) {
+                return TrainingResult {
+                    champion: self.population.get_champion().unwrap(),
+                    reason,
+                    report,
+                }
+            }
+        }
+    }
 
-            best_fitness = self.population.get_max_fitness();
+    /// Same as train_until(), but with an async fitness function. See step_async().
+    pub async fn train_until_async<F>(&mut self, env:F, termination:&Termination) -> TrainingResult
+    where F: AsyncFn(&Genome) -> f64
+    {
+        let (start, start_evaluations) = (Instant::now(), self.population.get_evaluations());
+        let mut generations = 0;
+        loop {
+            let report = self.step_async(&env).await;
+            generations += 1;
+            let progress = Progress { generations, evaluations: report.evaluations - start_evaluations, elapsed: start.elapsed() };
+            if let Some(reason) = termination.check(&report, &progress) {
+                return TrainingResult {
+                    champion: self.population.get_champion().unwrap(),
+                    reason,
+                    report,
+                }
+            }
         }
-        self.population.get_champion().unwrap().clone()
     }
 
+    /// Same as train(), but env also receives a shared context, such as a dataset or simulator configuration.
+    pub fn train_with_context<C, F>(&mut self, ctx:Arc<C>, env:F, target_fitness:f64) -> Genome 
+    where 
+        C: Send + Sync + 'static,
+        F: Fn(&Genome, &C) -> f64 + Send + Sync + 'static,
+    {
+        self.train(move |genome| env(genome, &ctx), target_fitness)
+    }
 
 
+
+    pub fn get_config(&self) -> &NeatConfig { &self.config }
+    pub(crate) fn get_rng_mut(&mut self) -> &mut NeatRng { &mut self.rng }
+    pub(crate) fn get_pools_mut(&mut self) -> (&mut HashMap<usize, NodeGene>, &mut HashMap<usize, ConnectionGene>) { (&mut self.node_pool, &mut self.connection_pool) }
     pub fn get_population(&self) -> &Population { &self.population }
     pub fn get_population_mut(&mut self) -> &mut Population { &mut self.population }
     pub fn get_node_pool(&self) -> &HashMap<usize, NodeGene> { &self.node_pool }
@@ -170,14 +402,16 @@ impl Neat {
 
     #[allow(dead_code)]
     pub fn new_xor() -> Self {
-        
+        let mut config = NeatConfig::new(2, 1);
+        let rng = Self::init_rng(&mut config);
+
         let mut node_pool:HashMap<usize, NodeGene> = HashMap::new();
-        node_pool.insert(0, NodeGene::new(crate::NodeType::Input, 0, 0.0, 0.75));
-        node_pool.insert(1, NodeGene::new(crate::NodeType::Input, 1, 0.0, 0.5));
-        node_pool.insert(2, NodeGene::new(crate::NodeType::Bias, 2, 0.0, 0.1));
-        node_pool.insert(3, NodeGene::new(crate::NodeType::Output, 3, 1.0, 0.5));
-        node_pool.insert(4, NodeGene::new(crate::NodeType::Hidden, 4, 0.5, 0.75));
-        node_pool.insert(5, NodeGene::new(crate::NodeType::Hidden, 5, 0.5, 0.5));
+        node_pool.insert(0, NodeGene::new(crate::NodeType::Input, 0, 0.0, 0.75, crate::ActivationFn::Identity));
+        node_pool.insert(1, NodeGene::new(crate::NodeType::Input, 1, 0.0, 0.5, crate::ActivationFn::Identity));
+        node_pool.insert(2, NodeGene::new(crate::NodeType::Bias, 2, 0.0, 0.1, crate::ActivationFn::Identity));
+        node_pool.insert(3, NodeGene::new(crate::NodeType::Output, 3, 1.0, 0.5, crate::ActivationFn::Sigmoid));
+        node_pool.insert(4, NodeGene::new(crate::NodeType::Hidden, 4, 0.5, 0.75, crate::ActivationFn::Sigmoid));
+        node_pool.insert(5, NodeGene::new(crate::NodeType::Hidden, 5, 0.5, 0.5, crate::ActivationFn::Sigmoid));
 
         let mut conn_pool:HashMap<usize, ConnectionGene> = HashMap::new();
         conn_pool.insert(conn_hashcode(0, 4), ConnectionGene::new_explicit(0, 20.0, true, 0, 4));
@@ -192,9 +426,14 @@ impl Neat {
         conn_pool.insert( conn_hashcode(2, 5), ConnectionGene::new_explicit(7, -30.0, true, 2, 5));
         conn_pool.insert( conn_hashcode(2, 3), ConnectionGene::new_explicit(8, 30.0, true, 2, 3));
         Self {
-            node_pool: node_pool.clone(),
+            population: Population::new(&node_pool, &config),
+            node_pool,
             connection_pool: conn_pool,
-            population: Population::new(&node_pool),
+            config,
+            started: false,
+            reporters: Vec::new(),
+            checkpointing: None,
+            rng,
         }
     }  
 }
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use rand::Rng;
+
+use crate::config::NeatConfig;
+use crate::genetics::{Genome, NodeGene, NodeType, ConnectionGene, conn_hashcode, Gene};
+
+// Provisional innovation numbers count up from here, far above any real innovation number
+const PROVISIONAL_BASE:usize = usize::MAX / 2;
+
+/// The innovation pools as seen by a single genome while the population mutates in parallel.
+/// The pools are read-only, so genes they do not contain yet are given provisional innovation numbers.
+/// Once every genome has mutated, register() swaps these for real ones genome by genome, so numbering does not depend on thread timing.
+pub struct Innovations<'a> {
+    node_pool: &'a HashMap<usize, NodeGene>,
+    connection_pool: &'a HashMap<usize, ConnectionGene>,
+    pending: Vec<Pending>, // in the order they were created
+}
+
+/// A gene which was missing from the pools when a genome mutated.
+pub enum Pending {
+    Node { innov:usize, split:usize }, // split is the connection the node replaced, which may itself be provisional
+    Connection { innov:usize, from:usize, to:usize, weight:f64 }, // weight is the random weight the pool stores
+}
+
+impl<'a> Innovations<'a> {
+
+    pub fn new(node_pool:&'a HashMap<usize, NodeGene>, connection_pool:&'a HashMap<usize, ConnectionGene>) -> Self {
+        Self { node_pool, connection_pool, pending: Vec::new() }
+    }
+
+    /// Returns the hidden node which replaces connection split, which lies between from & to.
+    /// Uses the pooled node if another genome has already evolved it.
+    pub fn node(&mut self, split:usize, from:&NodeGene, to:&NodeGene, config:&NeatConfig) -> NodeGene {
+        if split < PROVISIONAL_BASE {
+            if let Some(node) = self.node_pool.get(&(config.num_ibo() + split)) {
+                return node.clone()
+            }
+        }
+        let innov = self.next_provisional();
+        self.pending.push(Pending::Node { innov, split });
+        let mut node = NodeGene::new(
+            NodeType::Hidden,
+            innov,
+            (from.get_x() + to.get_x()) / 2.0,
+            (from.get_y() + to.get_y()) / 2.0,
+            config.hidden_activation,
+        );
+        node.set_aggregation(config.hidden_aggregation);
+        node
+    }
+
+    /// Returns a connection from one node to another with a random weight.
+    /// Uses the pooled connection, weight included, if another genome has already evolved it.
+    /// Otherwise register() gives it the weight of whichever genome registers it first, unless the caller sets its own weight.
+    pub fn connection<R: Rng + ?Sized>(&mut self, from:usize, to:usize, config:&NeatConfig, rng:&mut R) -> ConnectionGene {
+        let connection = ConnectionGene::new(self.next_provisional(), from, to, config, rng);
+        if from < PROVISIONAL_BASE && to < PROVISIONAL_BASE {
+            if let Some(pooled) = self.connection_pool.get(&conn_hashcode(from, to)) {
+                return pooled.clone()
+            }
+        }
+        self.pending.push(Pending::Connection { innov: connection.get_innov(), from, to, weight: connection.get_weight() });
+        connection
+    }
+
+    fn next_provisional(&self) -> usize { PROVISIONAL_BASE + self.pending.len() }
+
+    pub fn into_pending(self) -> Vec<Pending> { self.pending }
+}
+
+/// Swaps the provisional innovation numbers in a genome for real ones, adding its new genes to the pools.
+/// A gene registered first by an earlier genome keeps that genome's innovation number, and a new connection still
+/// carrying its random weight takes that genome's weight too, as if it had been pooled when this genome mutated.
+pub fn register(genome:&mut Genome, pending:Vec<Pending>, node_pool:&mut HashMap<usize, NodeGene>, connection_pool:&mut HashMap<usize, ConnectionGene>, config:&NeatConfig) {
+    if pending.is_empty() {
+        return
+    }
+
+    let mut real: HashMap<usize, usize> = HashMap::new();
+    let mut pooled_weights: HashMap<usize, (f64, f64)> = HashMap::new(); // provisional innovation -> (random weight, pooled weight)
+    let resolve = |innov:usize, real:&HashMap<usize, usize>| *real.get(&innov).unwrap_or(&innov);
+    for gene in pending {
+        match gene {
+            Pending::Node { innov, split } => {
+                let split = resolve(split, &real);
+                let template = genome.get_nodes().get_by_innov(innov);
+                let (x, y) = (template.get_x(), template.get_y());
+                let length = node_pool.len();
+                let node = node_pool.entry(config.num_ibo() + split).or_insert_with(|| {
+                    let mut node = NodeGene::new(NodeType::Hidden, length, x, y, config.hidden_activation);
+                    node.set_aggregation(config.hidden_aggregation);
+                    node
+                });
+                real.insert(innov, node.get_innov());
+            }
+            Pending::Connection { innov, from, to, weight } => {
+                let (from, to) = (resolve(from, &real), resolve(to, &real));
+                let length = connection_pool.len();
+                let connection = connection_pool.entry(conn_hashcode(from, to))
+                    .or_insert_with(|| ConnectionGene::new_explicit(length, weight, true, from, to));
+                real.insert(innov, connection.get_innov());
+                pooled_weights.insert(innov, (weight, connection.get_weight()));
+            }
+        }
+    }
+
+    // Reinsert every provisional gene under its real innovation number, keeping the genes sorted
+    let provisional_nodes: Vec<NodeGene> = genome.get_nodes().iter().filter(|node| node.get_innov() >= PROVISIONAL_BASE).cloned().collect();
+    for mut node in provisional_nodes {
+        genome.get_nodes_mut().remove(node.get_innov());
+        node.set_innov(real[&node.get_innov()]);
+        genome.get_nodes_mut().insert_sorted(node);
+    }
+    let provisional_connections: Vec<ConnectionGene> = genome.get_connections().iter().filter(|conn| conn.get_innov() >= PROVISIONAL_BASE).cloned().collect();
+    for conn in provisional_connections {
+        let weight = match pooled_weights[&conn.get_innov()] {
+            (random, pooled) if conn.get_weight() == random => pooled,
+            _ => conn.get_weight(), // set by the mutation, such as a split's weights
+        };
+        genome.get_connections_mut().remove(conn.get_innov());
+        genome.get_connections_mut().insert_sorted(ConnectionGene::new_explicit(
+            real[&conn.get_innov()],
+            weight,
+            conn.is_enabled(),
+            resolve(conn.get_from(), &real),
+            resolve(conn.get_to(), &real),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Neat, NeatConfig};
+    use crate::genetics::{Genome, Gene};
+    use super::{Innovations, register, PROVISIONAL_BASE};
+
+    #[test]
+    fn shared_innovations_get_one_number() {
+        let config = NeatConfig::new(2, 1);
+        let mut neat = Neat::new_fully_connected(config.clone());
+        let mut genomes = vec![neat.get_population().organisms.read().unwrap()[0].clone(); 2];
+        let (from, to) = (neat.get_node_pool()[&0].clone(), neat.get_node_pool()[&3].clone());
+
+        // Both genomes split the same connection, in parallel as far as the pools can tell
+        let mut pending = Vec::new();
+        for genome in genomes.iter_mut() {
+            let mut innovations = Innovations::new(neat.get_node_pool(), neat.get_connection_pool());
+            let node = innovations.node(0, &from, &to, &config);
+            let conn = innovations.connection(0, node.get_innov(), &config, &mut rand::thread_rng());
+            assert!(node.get_innov() >= PROVISIONAL_BASE && conn.get_innov() >= PROVISIONAL_BASE);
+            genome.get_nodes_mut().insert_sorted(node);
+            genome.get_connections_mut().insert_sorted(conn);
+            pending.push(innovations.into_pending());
+        }
+
+        let node_pool_len = neat.get_node_pool().len();
+        let (node_pool, connection_pool) = neat.get_pools_mut();
+        for (genome, pending) in genomes.iter_mut().zip(pending) {
+            register(genome, pending, node_pool, connection_pool, &config);
+        }
+        assert_eq!(neat.get_node_pool().len(), node_pool_len + 1);
+        // The second genome's connection takes the weight the first registered
+        let genes = |genome:&Genome| -> Vec<(usize, usize, usize, f64)> {
+            genome.get_connections().iter().map(|conn| (conn.get_innov(), conn.get_from(), conn.get_to(), conn.get_weight())).collect()
+        };
+        assert_eq!(genes(&genomes[0]), genes(&genomes[1]));
+        assert!(genomes[0].get_nodes().iter().all(|node| node.get_innov() < PROVISIONAL_BASE));
+        assert!(genomes[0].get_connections().iter().all(|conn| conn.get_innov() < PROVISIONAL_BASE && conn.get_to() < PROVISIONAL_BASE));
+    }
+}
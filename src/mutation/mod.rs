@@ -1,5 +1,6 @@
 mod mutations;
 mod mutate;
+mod innovations;
 
 pub use mutations::{
     mutate_new_node, 
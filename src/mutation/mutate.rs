@@ -1,28 +1,77 @@
+use std::sync::Arc;
 use crate::Neat;
+use crate::genetics::Genome;
+use crate::util::NeatRng;
 use rand::prelude::*;
-use crate::config::{POPULATION_SIZE, M_WEIGHT_SHIFT, M_WEIGHT_RANDOM, M_CONN_ENABLED, M_NODE, M_CONN};
-use super::mutations::{mutate_weight_shift, mutate_weight_random, mutate_conn_enabled, mutate_new_node, mutate_new_conn};
+use rayon::prelude::*;
+use super::innovations::{Innovations, Pending, register};
+use super::mutations::{
+    mutate_weight_shift, mutate_weight_random, mutate_conn_enabled, mutate_new_node, mutate_new_conn, mutate_activation, mutate_aggregation,
+    mutate_bias_perturb, mutate_bias_random, mutate_response_perturb, mutate_response_random, mutate_time_constant,
+};
 
+/// Mutates every genome which was not reproduced via elitism.
+/// Each genome draws from its own rng stream, seeded from the Neat rng, and genomes are mutated in parallel.
+/// New innovations are then registered in population order, so a seeded run is reproducible on any number of threads.
 pub fn mutate(neat:&mut Neat) {
-    let mut rand = rand::thread_rng();
+    let config = neat.get_config().clone();
+    let seeds: Vec<u64> = (0..config.population_size).map(|_| neat.get_rng_mut().gen()).collect();
+    let evaluator = neat.get_population_mut().get_evaluator().clone();
+    let organisms = Arc::clone(&neat.get_population().organisms);
+    let mut organisms = organisms.write().unwrap();
 
-    for g_id in 0..POPULATION_SIZE {
-        if neat.get_population().should_mutate(g_id) {
-            if rand.gen_range(0.0..1.0) < M_WEIGHT_SHIFT {
-                mutate_weight_shift(neat, g_id);
+    let (node_pool, connection_pool, population) = (neat.get_node_pool(), neat.get_connection_pool(), neat.get_population());
+    let genomes: &mut [Genome] = &mut organisms;
+    let pending: Vec<Vec<Pending>> = evaluator.install(|| {
+        genomes.par_iter_mut().zip(seeds.par_iter()).enumerate().map(|(g_id, (genome, seed))| {
+            let mut innovations = Innovations::new(node_pool, connection_pool);
+            let mut rand = NeatRng::seed_from_u64(*seed);
+            if population.should_mutate(g_id) {
+                if rand.gen_range(0.0..1.0) < config.m_weight_shift {
+                    mutate_weight_shift(genome, &config, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_weight_random {
+                    mutate_weight_random(genome, &config, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_conn_enabled {
+                    mutate_conn_enabled(genome, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_node {
+                    mutate_new_node(genome, &mut innovations, &config, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_conn {
+                    mutate_new_conn(genome, &mut innovations, &config, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_activation {
+                    mutate_activation(genome, &config, &mut rand);
+                }
+                if rand.gen_range(0.0..1.0) < config.m_aggregation {
+                    mutate_aggregation(genome, &config, &mut rand);
+                }
+                if config.node_parameters {
+                    if rand.gen_range(0.0..1.0) < config.m_bias_perturb {
+                        mutate_bias_perturb(genome, &config, &mut rand);
+                    }
+                    if rand.gen_range(0.0..1.0) < config.m_bias_random {
+                        mutate_bias_random(genome, &config, &mut rand);
+                    }
+                    if rand.gen_range(0.0..1.0) < config.m_response_perturb {
+                        mutate_response_perturb(genome, &config, &mut rand);
+                    }
+                    if rand.gen_range(0.0..1.0) < config.m_response_random {
+                        mutate_response_random(genome, &config, &mut rand);
+                    }
+                }
+                if rand.gen_range(0.0..1.0) < config.m_time_constant {
+                    mutate_time_constant(genome, &config, &mut rand);
+                }
             }
-            if rand.gen_range(0.0..1.0) < M_WEIGHT_RANDOM {
-                mutate_weight_random(neat, g_id);
-            }
-            if rand.gen_range(0.0..1.0) < M_CONN_ENABLED {
-                mutate_conn_enabled(neat, g_id);
-            }
-            if rand.gen_range(0.0..1.0) < M_NODE {
-                mutate_new_node(neat, g_id);
-            }
-            if rand.gen_range(0.0..1.0) < M_CONN {
-                mutate_new_conn(neat, g_id);
-            }
-        }
+            innovations.into_pending()
+        }).collect()
+    });
+
+    let (node_pool, connection_pool) = neat.get_pools_mut();
+    for (genome, pending) in organisms.iter_mut().zip(pending) {
+        register(genome, pending, node_pool, connection_pool, &config);
     }
-}
\ No newline at end of file
+}
@@ -1,66 +1,38 @@
 use rand::prelude::*;
-use crate::genetics::{NodeGene, NodeType, ConnectionGene, conn_hashcode, Gene};
-use crate::config::{MAX_ITER, INPUTS, BIAS, OUTPUTS, WEIGHT_RANDOM_MAX, WEIGHT_SHIFT_MAX_PCT};
-use crate::Neat;
+use crate::config::NeatConfig;
+use crate::genetics::{Genome, NodeType, Gene};
+use crate::util::{NeatRng, ActivationFn, AggregationFn};
+use super::innovations::Innovations;
 
 /// Mutates a new node into a genome by splitting a random existing connection into two new connections with a new node in between.
 /// Fails if and only if the genome does not have any connections
 /// Checks the node_pool to check whether the new node has already been evolved by other genomes, and if so uses this node.
-pub fn mutate_new_node(neat:&mut Neat, g_id:usize) -> bool {
-    
-    if neat.get_population().organisms.read().unwrap()[g_id].get_connections().len() == 0 {
+pub fn mutate_new_node(genome:&mut Genome, innovations:&mut Innovations, config:&NeatConfig, rng:&mut NeatRng) -> bool {
+    if genome.get_connections().len() == 0 {
         return false
     }
 
-    let mut rand_conn_innov = neat.get_population().organisms.read().unwrap()[g_id].get_connections().rand_innov();
-    let mut from = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_from();
-    let mut to = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_to();
-
+    // Pooled connections are always enabled, so only connections from the bias are rejected
+    let mut rand_conn_innov = genome.get_connections().rand_innov(rng);
     let mut iter = 0;
-    while !neat.get_connection_pool().get(&conn_hashcode(from, to)).unwrap().is_enabled() || neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(from).get_nodetype() == NodeType::Bias {
+    while genome.get_nodes().get_by_innov(genome.get_connections().get_by_innov(rand_conn_innov).get_from()).get_nodetype() == NodeType::Bias {
         iter += 1;
-        if iter > MAX_ITER {
+        if iter > config.max_iter {
             return false
         }
-        rand_conn_innov = neat.get_population().organisms.read().unwrap()[g_id].get_connections().rand_innov();
-        from = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_from();
-        to = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_to();
+        rand_conn_innov = genome.get_connections().rand_innov(rng);
     }
 
-    let weight = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_weight();
-    let from_x = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(from).get_x();
-    let from_y = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(from).get_y();
-    let to_x = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(to).get_x();
-    let to_y = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(to).get_y();
-    
-    let new_node_guid = INPUTS + BIAS as usize + OUTPUTS + rand_conn_innov;
-    let length = neat.get_node_pool().len();
-    let new_node = neat.get_node_pool_mut().entry(new_node_guid)
-        .or_insert(NodeGene::new(
-            NodeType::Hidden, 
-            length,
-            (from_x + to_x) / 2.0,
-            (from_y + to_y) / 2.0,
-       ))
-        .clone();
+    let old_conn = genome.get_connections().get_by_innov(rand_conn_innov);
+    let (from, to, weight) = (old_conn.get_from(), old_conn.get_to(), old_conn.get_weight());
+    let new_node = innovations.node(rand_conn_innov, genome.get_nodes().get_by_innov(from), genome.get_nodes().get_by_innov(to), config);
     let new_node_innov = new_node.get_innov();
 
-    let new_conn_1_guid = conn_hashcode(from, new_node_innov);
-    let new_conn_2_guid = conn_hashcode(new_node_innov, to);
-
-    let conn_gene_pool_len = neat.get_connection_pool().len();
-    let mut new_conn_1 = neat.get_connection_pool_mut().entry(new_conn_1_guid)
-        .or_insert(ConnectionGene::new(conn_gene_pool_len, from, new_node_innov))
-        .clone();
+    let mut new_conn_1 = innovations.connection(from, new_node_innov, config, rng);
     new_conn_1.set_weight(1.0);
-
-    let conn_gene_pool_len = neat.get_connection_pool().len();
-    let mut new_conn_2 = neat.get_connection_pool_mut().entry(new_conn_2_guid)
-        .or_insert(ConnectionGene::new(conn_gene_pool_len, new_node_innov, to))
-        .clone();
+    let mut new_conn_2 = innovations.connection(new_node_innov, to, config, rng);
     new_conn_2.set_weight(weight);
 
-    let genome = &mut neat.get_population().organisms.write().unwrap()[g_id];
     genome.get_connections_mut().remove(rand_conn_innov);
     genome.get_connections_mut().insert_sorted(new_conn_1);
     genome.get_connections_mut().insert_sorted(new_conn_2);
@@ -72,68 +44,154 @@ pub fn mutate_new_node(neat:&mut Neat, g_id:usize) -> bool {
 //dont mutate link when from => ... => to already exists
 /// Mutates a new connection in a genome by selecting two random nodes.
 /// Ensures that these nodes are not already connected, even by intermediaries, to prevent duplicates.
-/// Will attempt to find a suitable connection MAX_ITER times before failing.
-pub fn mutate_new_conn(neat:&mut Neat, g_id:usize) -> bool {
-
-    // let mut rand_conn_innov = neat.get_population().organisms.read().unwrap()[g_id].get_connections().rand_innov();
-    // let mut from = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_from();
-    // let mut to = neat.get_population().organisms.read().unwrap()[g_id].get_connections().get_by_innov(rand_conn_innov).get_to();
-    //select rand from_node in genome
-    // select rand to_node in genome
-    //if  they are already connected (directly or indirectly), then choose again until they arent or until iter > MAX_ITER
-
-    for _i in 0..MAX_ITER{
-        let mut from_innov = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().rand_innov();
-        let mut to_innov = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().rand_innov();
-        let from_x = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(from_innov).get_x();
-        let to_x = neat.get_population().organisms.read().unwrap()[g_id].get_nodes().get_by_innov(to_innov).get_x();
-        if from_x == to_x {
-            continue;
-        } else if from_x > to_x {
-            let temp = from_innov;
-            from_innov = to_innov;
-            to_innov = temp;
-        }
-
-        if neat.get_population().organisms.read().unwrap()[g_id].is_connected(from_innov, to_innov) {
-            continue;
+/// In recurrent mode any node may instead connect to any hidden or output node, including itself, unless they are already directly connected.
+/// Will attempt to find a suitable connection config.max_iter times before failing.
+pub fn mutate_new_conn(genome:&mut Genome, innovations:&mut Innovations, config:&NeatConfig, rng:&mut NeatRng) -> bool {
+    for _i in 0..config.max_iter {
+        let mut from_innov = genome.get_nodes().rand_innov(rng);
+        let mut to_innov = genome.get_nodes().rand_innov(rng);
+
+        if config.recurrent {
+            if matches!(genome.get_nodes().get_by_innov(to_innov).get_nodetype(), NodeType::Input | NodeType::Bias) {
+                continue;
+            }
+            if genome.get_connections().iter().any(|conn| conn.get_from() == from_innov && conn.get_to() == to_innov) {
+                continue;
+            }
+        } else {
+            let from_x = genome.get_nodes().get_by_innov(from_innov).get_x();
+            let to_x = genome.get_nodes().get_by_innov(to_innov).get_x();
+            if from_x == to_x {
+                continue;
+            } else if from_x > to_x {
+                std::mem::swap(&mut from_innov, &mut to_innov);
+            }
+
+            if genome.is_connected(from_innov, to_innov) {
+                continue;
+            }
         }
 
-        let length = neat.get_connection_pool().len();
-        let new_connection = neat.get_connection_pool_mut().entry(conn_hashcode(from_innov, to_innov))
-            .or_insert(ConnectionGene::new(length, from_innov, to_innov))
-            .clone();
-        
-        neat.get_population().organisms.write().unwrap()[g_id].get_connections_mut().insert_sorted(new_connection);
+        let new_connection = innovations.connection(from_innov, to_innov, config, rng);
+        genome.get_connections_mut().insert_sorted(new_connection);
         return true
     }
     false
 }
 
 /// Toggle the 'enabled' boolean of a random connection in the genome.
-pub fn mutate_conn_enabled(neat:&mut Neat, g_id:usize) {
-    let genome = &mut neat.get_population().organisms.write().unwrap()[g_id];
+pub fn mutate_conn_enabled(genome:&mut Genome, rng:&mut NeatRng) {
     if genome.get_connections().len() > 0 {
-        let rand_connection = genome.get_connections_mut().rand_element_mut();
+        let rand_connection = genome.get_connections_mut().rand_element_mut(rng);
         rand_connection.set_enabled(!rand_connection.is_enabled());
     }
 }
 
-/// Mutates the weight of a random connection in a genome to between the range +-WEIGHT_RANDOM_MAX
-pub fn mutate_weight_random(neat:&mut Neat, g_id:usize) {
-    let genome = &mut neat.get_population().organisms.write().unwrap()[g_id];
+/// Changes the activation function of a random hidden node to another of config.activation_options.
+/// Fails if the genome has no hidden nodes, or there is no other option to choose from.
+pub fn mutate_activation(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) -> bool {
+    let hidden_innovs: Vec<usize> = genome.get_nodes().iter()
+        .filter(|node| node.get_nodetype() == NodeType::Hidden)
+        .map(|node| node.get_innov())
+        .collect();
+    if hidden_innovs.is_empty() {
+        return false
+    }
+    let node = genome.get_nodes_mut().get_by_innov_mut(hidden_innovs[rng.gen_range(0..hidden_innovs.len())]);
+
+    let choices: Vec<ActivationFn> = config.activation_options.iter().copied().filter(|a| *a != node.get_activation()).collect();
+    if choices.is_empty() {
+        return false
+    }
+    node.set_activation(choices[rng.gen_range(0..choices.len())]);
+    true
+}
+
+/// Changes the aggregation function of a random hidden node to another of config.aggregation_options.
+/// Fails if the genome has no hidden nodes, or there is no other option to choose from.
+pub fn mutate_aggregation(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) -> bool {
+    let hidden_innovs: Vec<usize> = genome.get_nodes().iter()
+        .filter(|node| node.get_nodetype() == NodeType::Hidden)
+        .map(|node| node.get_innov())
+        .collect();
+    if hidden_innovs.is_empty() {
+        return false
+    }
+    let node = genome.get_nodes_mut().get_by_innov_mut(hidden_innovs[rng.gen_range(0..hidden_innovs.len())]);
+
+    let choices: Vec<AggregationFn> = config.aggregation_options.iter().copied().filter(|a| *a != node.get_aggregation()).collect();
+    if choices.is_empty() {
+        return false
+    }
+    node.set_aggregation(choices[rng.gen_range(0..choices.len())]);
+    true
+}
+
+/// Returns the innovation number of a random hidden or output node, the nodes which carry a bias, response & time constant.
+fn rand_parameter_node(genome:&Genome, rng:&mut NeatRng) -> Option<usize> {
+    let innovs: Vec<usize> = genome.get_nodes().iter()
+        .filter(|node| matches!(node.get_nodetype(), NodeType::Hidden | NodeType::Output))
+        .map(|node| node.get_innov())
+        .collect();
+    if innovs.is_empty() {
+        None
+    } else {
+        Some(innovs[rng.gen_range(0..innovs.len())])
+    }
+}
+
+/// Shifts the bias of a random hidden or output node by a value in range +- bias_perturb_max
+pub fn mutate_bias_perturb(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
+    if let Some(innov) = rand_parameter_node(genome, rng) {
+        let node = genome.get_nodes_mut().get_by_innov_mut(innov);
+        node.set_bias(node.get_bias() + rng.gen_range(-1.0..1.0) * config.bias_perturb_max);
+    }
+}
+
+/// Replaces the bias of a random hidden or output node with a value in range +- bias_random_max
+pub fn mutate_bias_random(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
+    if let Some(innov) = rand_parameter_node(genome, rng) {
+        genome.get_nodes_mut().get_by_innov_mut(innov).set_bias(rng.gen_range(-1.0..1.0) * config.bias_random_max);
+    }
+}
+
+/// Shifts the response of a random hidden or output node by a value in range +- response_perturb_max
+pub fn mutate_response_perturb(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
+    if let Some(innov) = rand_parameter_node(genome, rng) {
+        let node = genome.get_nodes_mut().get_by_innov_mut(innov);
+        node.set_response(node.get_response() + rng.gen_range(-1.0..1.0) * config.response_perturb_max);
+    }
+}
+
+/// Replaces the response of a random hidden or output node with a value in range 1 +- response_random_max
+pub fn mutate_response_random(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
+    if let Some(innov) = rand_parameter_node(genome, rng) {
+        genome.get_nodes_mut().get_by_innov_mut(innov).set_response(1.0 + rng.gen_range(-1.0..1.0) * config.response_random_max);
+    }
+}
+
+/// Shifts the time constant of a random hidden or output node by a value in range +- time_constant_perturb_max,
+/// keeping it between time_constant_min & time_constant_max.
+pub fn mutate_time_constant(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
+    if let Some(innov) = rand_parameter_node(genome, rng) {
+        let node = genome.get_nodes_mut().get_by_innov_mut(innov);
+        node.set_time_constant((node.get_time_constant() + rng.gen_range(-1.0..1.0) * config.time_constant_perturb_max).clamp(config.time_constant_min, config.time_constant_max));
+    }
+}
+
+/// Mutates the weight of a random connection in a genome to between the range +-weight_random_max
+pub fn mutate_weight_random(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
     if genome.get_connections().len() > 0 {
-        let rand_connection = genome.get_connections_mut().rand_element_mut();
-        rand_connection.set_weight(rand::thread_rng().gen_range(-1.0..1.0) * WEIGHT_RANDOM_MAX);
+        let rand_connection = genome.get_connections_mut().rand_element_mut(rng);
+        rand_connection.set_weight(rng.gen_range(-1.0..1.0) * config.weight_random_max);
     }
 }
 
-/// Mutates the weight of a random connection in a genome by a shift value in range +- WEIGHT_SHIFT_MAX_PCT
-pub fn mutate_weight_shift(neat:&mut Neat, g_id:usize) {
-    let genome = &mut neat.get_population().organisms.write().unwrap()[g_id];
+/// Mutates the weight of a random connection in a genome by a shift value in range +- weight_shift_max_pct
+pub fn mutate_weight_shift(genome:&mut Genome, config:&NeatConfig, rng:&mut NeatRng) {
     if genome.get_connections().len() > 0 {
-        let rand_connection = genome.get_connections_mut().rand_element_mut();
-        rand_connection.set_weight(rand_connection.get_weight() * (1.0 + WEIGHT_SHIFT_MAX_PCT * rand::thread_rng().gen_range(-1..1) as f64));
+        let rand_connection = genome.get_connections_mut().rand_element_mut(rng);
+        rand_connection.set_weight(rand_connection.get_weight() * (1.0 + config.weight_shift_max_pct * rng.gen_range(-1..1) as f64));
     }
 }
 
@@ -146,11 +204,14 @@ pub fn mutate_weight_shift(neat:&mut Neat, g_id:usize) {
 
 #[cfg(test)]
 mod tests {
-    use crate::Neat;
-    use crate::genetics::{NodeGene, NodeType, ConnectionGene, Genome, conn_hashcode};
-    use std::collections::HashMap;
+    use crate::{Neat, NeatConfig};
+    use crate::genetics::{NodeGene, NodeType, Genome};
 
-    use super::{mutate_new_node, mutate_new_conn, mutate_conn_enabled};
+    use rand::SeedableRng;
+    use crate::util::{NeatRng, AggregationFn};
+    use crate::genetics::Gene;
+    use super::super::innovations::{Innovations, register};
+    use super::{mutate_new_conn, mutate_activation, mutate_aggregation, mutate_bias_random};
 
     // #[test]
     // pub fn test_mutate_new_node() {
@@ -222,4 +283,54 @@ mod tests {
     //     mutate_conn_enabled(&mut neat, 0);
     //     assert!(!neat.get_population().get_genome(0).get_connections().get(0).is_enabled());
     // }
-}
\ No newline at end of file
+
+    #[test]
+    fn mutate_activation_changes_a_hidden_node() {
+        let mut genome = Genome::new_init_xor();
+        assert!(mutate_activation(&mut genome, &NeatConfig::new(2, 1), &mut NeatRng::seed_from_u64(0)));
+        let original = Genome::new_init_xor();
+        let changed: Vec<&NodeGene> = genome.get_nodes().iter()
+            .filter(|node| node.get_activation() != original.get_nodes().get_by_innov(node.get_innov()).get_activation())
+            .collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].get_nodetype(), NodeType::Hidden);
+    }
+
+    #[test]
+    fn mutate_bias_random_changes_a_node_bias() {
+        let mut genome = Genome::new_init_xor();
+        mutate_bias_random(&mut genome, &NeatConfig::new(2, 1), &mut NeatRng::seed_from_u64(0));
+        let biased: Vec<&NodeGene> = genome.get_nodes().iter().filter(|node| node.get_bias() != 0.0).collect();
+        assert_eq!(biased.len(), 1);
+        assert!(matches!(biased[0].get_nodetype(), NodeType::Hidden | NodeType::Output));
+    }
+
+    #[test]
+    fn mutate_aggregation_changes_a_hidden_node() {
+        let mut genome = Genome::new_init_xor();
+        assert!(mutate_aggregation(&mut genome, &NeatConfig::new(2, 1), &mut NeatRng::seed_from_u64(0)));
+        let changed: Vec<&NodeGene> = genome.get_nodes().iter().filter(|node| node.get_aggregation() != AggregationFn::Sum).collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].get_nodetype(), NodeType::Hidden);
+    }
+
+    #[test]
+    fn recurrent_mutate_new_conn_adds_self_loops() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 10;
+        config.recurrent = true;
+        config.seed = Some(0);
+        let mut neat = Neat::new_fully_connected(config.clone());
+        let mut genome = neat.get_population().organisms.read().unwrap()[0].clone();
+
+        // Every forward connection already exists, so the only new connection is the output's self loop
+        let mut innovations = Innovations::new(neat.get_node_pool(), neat.get_connection_pool());
+        assert!(mutate_new_conn(&mut genome, &mut innovations, &config, &mut NeatRng::seed_from_u64(0)));
+        let pending = innovations.into_pending();
+        let (node_pool, connection_pool) = neat.get_pools_mut();
+        register(&mut genome, pending, node_pool, connection_pool, &config);
+
+        assert!(genome.get_connections().iter().any(|conn| conn.get_from() == 3 && conn.get_to() == 3));
+        assert!(genome.validate(&config).is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use ndarray::{s, Array1, Array2};
+
+use crate::genetics::{Genome, Gene, NodeType};
+use crate::util::{ActivationFn, AggregationFn};
+
+/// A feed-forward network compiled into flat arrays for fast inference.
+/// Only the nodes the outputs depend on are kept, sorted so each node comes after its inputs.
+/// Gives bit-identical outputs to a FeedForwardNetwork built from the same genome, without allocating on each activation.
+pub struct CompiledNetwork {
+    activations: Vec<f64>, // one slot per node: inputs, then the bias, then evaluated nodes in topological order
+    num_inputs: usize,
+    bias_slot: Option<usize>,
+    nodes: Vec<CompiledNode>, // in topological order
+    sources: Vec<usize>, // activation slot of each node's inputs, stored contiguously node by node
+    weights: Vec<f64>, // weight of each entry in sources
+    output_slots: Vec<usize>,
+    outputs: Vec<f64>,
+    weighted_inputs: Vec<f64>, // scratch buffer, sized to the largest number of inputs to a node
+}
+
+struct CompiledNode {
+    slot: usize,
+    inputs: std::ops::Range<usize>, // range of sources & weights holding this node's inputs
+    activation_fn: ActivationFn,
+    aggregation_fn: AggregationFn,
+    bias: f64,
+    response: f64,
+}
+
+impl CompiledNetwork {
+
+    /// Compiles a feed-forward genome. Genomes with cycles are not supported.
+    pub fn new(genome:&Genome) -> CompiledNetwork {
+
+        // Enabled inputs of each node, in the order FeedForwardNetwork would sum them
+        let mut incoming: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+        for conn in genome.get_connections().iter() {
+            if conn.is_enabled() {
+                incoming.entry(conn.get_to()).or_default().push((conn.get_from(), conn.get_weight()));
+            }
+        }
+
+        let mut slots: HashMap<usize, usize> = HashMap::new();
+        let mut bias_slot = None;
+        for node in genome.get_nodes().iter().filter(|node| node.get_nodetype() == NodeType::Input) {
+            slots.insert(node.get_innov(), slots.len());
+        }
+        let num_inputs = slots.len();
+        if let Some(bias) = genome.get_nodes().iter().find(|node| node.get_nodetype() == NodeType::Bias) {
+            bias_slot = Some(slots.len());
+            slots.insert(bias.get_innov(), slots.len());
+        }
+
+        // Depth first from each output, so every node is ordered after its inputs
+        let output_ids: Vec<usize> = genome.get_nodes().iter()
+            .filter(|node| node.get_nodetype() == NodeType::Output)
+            .map(|node| node.get_innov())
+            .collect();
+        let mut order = Vec::new();
+        for id in output_ids.iter() {
+            topological_sort(*id, &incoming, &mut slots, &mut order);
+        }
+
+        let mut network = CompiledNetwork {
+            activations: vec![0.0; slots.len()],
+            num_inputs,
+            bias_slot,
+            nodes: Vec::with_capacity(order.len()),
+            sources: Vec::new(),
+            weights: Vec::new(),
+            output_slots: output_ids.iter().map(|id| slots[id]).collect(),
+            outputs: vec![0.0; output_ids.len()],
+            weighted_inputs: Vec::new(),
+        };
+
+        let mut max_inputs = 0;
+        for id in order {
+            let node_gene = genome.get_nodes().get_by_innov(id);
+            let start = network.sources.len();
+            for (from, weight) in incoming.get(&id).map(|inputs| inputs.as_slice()).unwrap_or(&[]) {
+                network.sources.push(slots[from]);
+                network.weights.push(*weight);
+            }
+            max_inputs = max_inputs.max(network.sources.len() - start);
+            network.nodes.push(CompiledNode {
+                slot: slots[&id],
+                inputs: start..network.sources.len(),
+                activation_fn: node_gene.get_activation(),
+                aggregation_fn: node_gene.get_aggregation(),
+                bias: node_gene.get_bias(),
+                response: node_gene.get_response(),
+            });
+        }
+        network.weighted_inputs = vec![0.0; max_inputs];
+
+        network
+    }
+
+    /// Feeds a supplied input into the net and returns the calculated output, which is overwritten by the next activation.
+    pub fn activate(&mut self, inputs:&[f64]) -> &[f64] {
+        if inputs.len() != self.num_inputs {
+            panic!("CompiledNetwork received input of incorrect length");
+        }
+
+        self.activations[..self.num_inputs].copy_from_slice(inputs);
+        if let Some(bias_slot) = self.bias_slot {
+            self.activations[bias_slot] = -1.0;
+        }
+
+        for node in self.nodes.iter() {
+            let weighted_inputs = &mut self.weighted_inputs[..node.inputs.len()];
+            for (i, j) in node.inputs.clone().enumerate() {
+                weighted_inputs[i] = self.activations[self.sources[j]] * self.weights[j];
+            }
+            let aggregate = node.aggregation_fn.apply(weighted_inputs);
+            self.activations[node.slot] = node.activation_fn.apply(node.bias + node.response * aggregate);
+        }
+
+        for (output, slot) in self.outputs.iter_mut().zip(self.output_slots.iter()) {
+            *output = self.activations[*slot];
+        }
+        &self.outputs
+    }
+
+    /// Evaluates a batch of samples, one per row of inputs, and returns one row of outputs per sample.
+    /// Each node is evaluated for every sample before moving on to the next. Rows match those given by activate() exactly.
+    pub fn activate_batch(&mut self, inputs:&Array2<f64>) -> Array2<f64> {
+        if inputs.ncols() != self.num_inputs {
+            panic!("CompiledNetwork received input of incorrect length");
+        }
+
+        // One row per node, one column per sample
+        let samples = inputs.nrows();
+        let mut activations = Array2::<f64>::zeros((self.activations.len(), samples));
+        activations.slice_mut(s![..self.num_inputs, ..]).assign(&inputs.t());
+        if let Some(bias_slot) = self.bias_slot {
+            activations.row_mut(bias_slot).fill(-1.0);
+        }
+
+        let mut aggregates = Array1::<f64>::zeros(samples);
+        for node in self.nodes.iter() {
+            if node.aggregation_fn == AggregationFn::Sum {
+                // Summed in the same order as AggregationFn::apply, so results are identical
+                aggregates.fill(0.0);
+                for j in node.inputs.clone() {
+                    let weight = self.weights[j];
+                    aggregates.zip_mut_with(&activations.row(self.sources[j]), |acc, activation| *acc += activation * weight);
+                }
+            } else {
+                let weighted_inputs = &mut self.weighted_inputs[..node.inputs.len()];
+                for sample in 0..samples {
+                    for (i, j) in node.inputs.clone().enumerate() {
+                        weighted_inputs[i] = activations[[self.sources[j], sample]] * self.weights[j];
+                    }
+                    aggregates[sample] = node.aggregation_fn.apply(weighted_inputs);
+                }
+            }
+            activations.row_mut(node.slot).zip_mut_with(&aggregates, |activation, aggregate| {
+                *activation = node.activation_fn.apply(node.bias + node.response * aggregate);
+            });
+        }
+
+        let mut outputs = Array2::<f64>::zeros((samples, self.output_slots.len()));
+        for (k, slot) in self.output_slots.iter().enumerate() {
+            outputs.column_mut(k).assign(&activations.row(*slot));
+        }
+        outputs
+    }
+
+    pub fn num_inputs(&self) -> usize { self.num_inputs }
+    pub fn num_outputs(&self) -> usize { self.output_slots.len() }
+    pub fn has_bias(&self) -> bool { self.bias_slot.is_some() }
+
+}
+
+/// Appends node and every node it depends on to order, inputs first, giving each a slot in the activation buffer.
+fn topological_sort(node:usize, incoming:&HashMap<usize, Vec<(usize, f64)>>, slots:&mut HashMap<usize, usize>, order:&mut Vec<usize>) {
+    if slots.contains_key(&node) {
+        return
+    }
+    // Claim the slot before visiting inputs, so a cycle cannot recurse forever
+    slots.insert(node, slots.len());
+    if let Some(inputs) = incoming.get(&node) {
+        for (from, _) in inputs.iter() {
+            topological_sort(*from, incoming, slots, order);
+        }
+    }
+    order.push(node);
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+    use rand::{Rng, SeedableRng};
+    use crate::{Neat, NeatConfig, NeatRng, Termination};
+    use crate::neural_network::FeedForwardNetwork;
+    use crate::test_environments::xor;
+    use crate::genetics::Genome;
+    use super::CompiledNetwork;
+
+    fn evolved_genomes() -> Vec<Genome> {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.m_node = 0.3;
+        config.m_conn = 0.3;
+        config.m_conn_enabled = 0.2;
+        config.m_activation = 0.2;
+        config.m_aggregation = 0.2;
+        config.node_parameters = true;
+        config.seed = Some(9);
+        let mut neat = Neat::new_fully_connected(config);
+        neat.train_until(xor, &Termination { max_generations: Some(20), ..Termination::default() });
+        let genomes = neat.get_population().organisms.read().unwrap().clone();
+        genomes
+    }
+
+    #[test]
+    fn outputs_are_bit_identical() {
+        let mut rng = NeatRng::seed_from_u64(0);
+        for genome in evolved_genomes().iter() {
+            let mut original = FeedForwardNetwork::new(genome);
+            let mut compiled = CompiledNetwork::new(genome);
+            for _ in 0..20 {
+                let inputs = vec![rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)];
+                let expected = original.activate(inputs.clone());
+                let actual = compiled.activate(&inputs);
+                assert_eq!(expected[0].to_bits(), actual[0].to_bits(), "{} != {}", expected[0], actual[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn batch_matches_single_activations() {
+        let mut rng = NeatRng::seed_from_u64(1);
+        let inputs = Array2::from_shape_fn((32, 2), |_| rng.gen_range(-5.0..5.0));
+        for genome in evolved_genomes().iter() {
+            let mut compiled = CompiledNetwork::new(genome);
+            let outputs = compiled.activate_batch(&inputs);
+            assert_eq!(outputs.dim(), (32, 1));
+            for (row, output) in inputs.rows().into_iter().zip(outputs.rows()) {
+                let expected = compiled.activate(row.as_slice().unwrap())[0];
+                assert_eq!(expected.to_bits(), output[0].to_bits(), "{} != {}", expected, output[0]);
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::genetics::{Genome, Gene, NodeType};
+use super::node::Node;
+
+/// A continuous-time recurrent neural network, which may contain back edges & self loops.
+/// Each hidden and output node moves towards the value its inputs drive it to at a rate set by its time constant:
+/// dy/dt = (f(bias + response * aggregate(weighted inputs)) - y) / time_constant
+pub struct Ctrnn {
+    nodes: HashMap<usize, Node>, // innovation_number -> Node. Connections are stored in their 'to' Nodes
+    time_constants: HashMap<usize, f64>, // innovation_number -> time constant, for hidden & output nodes
+    node_ids: Vec<usize>, // sorted, so nodes are always updated in the same order
+    input_ids: Vec<usize>,
+    bias_id: Option<usize>,
+    output_ids: Vec<usize>,
+    time: f64, // simulated time since construction or the last reset
+}
+
+impl Ctrnn {
+
+    /// Constructs a new CTRNN from a given genome, with every activation starting at 0.
+    pub fn new(genome:&Genome) -> Ctrnn {
+
+        let mut network = Ctrnn {
+            nodes: HashMap::new(),
+            time_constants: HashMap::new(),
+            node_ids: Vec::new(),
+            input_ids: Vec::new(),
+            bias_id: None,
+            output_ids: Vec::new(),
+            time: 0.0,
+        };
+
+        for node_gene in genome.get_nodes().iter() {
+            network.nodes.insert(node_gene.get_innov(), Node::new(node_gene.get_activation(), node_gene.get_aggregation(), node_gene.get_bias(), node_gene.get_response()));
+            match node_gene.get_nodetype() {
+                NodeType::Input => network.input_ids.push(node_gene.get_innov()),
+                NodeType::Bias => network.bias_id = Some(node_gene.get_innov()),
+                NodeType::Output => network.output_ids.push(node_gene.get_innov()),
+                NodeType::Hidden => (),
+            }
+            if matches!(node_gene.get_nodetype(), NodeType::Hidden | NodeType::Output) {
+                network.time_constants.insert(node_gene.get_innov(), node_gene.get_time_constant());
+                network.node_ids.push(node_gene.get_innov());
+            }
+        }
+
+        for conn_gene in genome.get_connections().iter() {
+            if conn_gene.is_enabled() {
+                let to = network.nodes.get_mut(&conn_gene.get_to()).unwrap();
+                to.push(conn_gene.get_from(), conn_gene.get_weight());
+            }
+        }
+
+        network
+    }
+
+    /// Holds the inputs fixed and integrates the network forward by total_time using forward-Euler steps of at most dt,
+    /// then returns the outputs. The last step is shortened so exactly total_time passes.
+    /// Euler integration is only stable while dt stays below the smallest time constant.
+    pub fn advance(&mut self, inputs:Vec<f64>, dt:f64, total_time:f64) -> Vec<f64> {
+        if inputs.len() != self.input_ids.len() {
+            panic!("Ctrnn received input of incorrect length");
+        }
+        if dt.is_nan() || dt <= 0.0 {
+            panic!("Ctrnn step size must be above 0, got {}", dt);
+        }
+        if !(total_time.is_finite() && total_time >= 0.0) {
+            panic!("Ctrnn total_time must be finite and not negative, got {}", total_time);
+        }
+
+        for (id, input) in self.input_ids.iter().zip(inputs.iter()) {
+            self.nodes.get_mut(id).unwrap().set_activation(Some(*input));
+        }
+        if let Some(bias_id) = self.bias_id {
+            self.nodes.get_mut(&bias_id).unwrap().set_activation(Some(-1.0));
+        }
+
+        let mut elapsed = 0.0;
+        while elapsed < total_time {
+            let step = dt.min(total_time - elapsed);
+            self.step(step);
+            elapsed += step;
+        }
+        self.time += elapsed;
+
+        self.output_ids.iter().map(|id| self.get_activation(*id).unwrap_or(0.0)).collect()
+    }
+
+    /// Takes a single Euler step of size dt, where every node reads the activations of the previous step.
+    fn step(&mut self, dt:f64) {
+        let previous: HashMap<usize, f64> = self.nodes.iter()
+            .map(|(id, node)| (*id, node.get_activation().unwrap_or(0.0)))
+            .collect();
+
+        for id in self.node_ids.iter() {
+            let node = self.nodes.get(id).unwrap();
+            let weighted_inputs: Vec<f64> = node.get_inputs_node_ids().iter()
+                .zip(node.get_weights().iter())
+                .map(|(input_id, weight)| previous[input_id] * weight)
+                .collect();
+            let aggregate = node.get_aggregation_fn().apply(&weighted_inputs);
+            let target = node.get_activation_fn().apply(node.get_bias() + node.get_response() * aggregate);
+            let new_activation = previous[id] + dt / self.time_constants[id] * (target - previous[id]);
+            self.nodes.get_mut(id).unwrap().set_activation(Some(new_activation));
+        }
+    }
+
+    /// Returns every activation to 0 and the simulated time to 0.
+    pub fn reset(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.set_activation(None);
+        }
+        self.time = 0.0;
+    }
+
+    /// Returns the activation of a node, or None if the network has not been advanced since it was reset.
+    pub fn get_activation(&self, node_id:usize) -> Option<f64> {
+        self.nodes.get(&node_id).and_then(|node| *node.get_activation())
+    }
+
+    /// Returns the simulated time which has passed since construction or the last reset.
+    pub fn get_time(&self) -> f64 { self.time }
+    pub fn num_inputs(&self) -> usize { self.input_ids.len() }
+    pub fn num_outputs(&self) -> usize { self.output_ids.len() }
+    pub fn has_bias(&self) -> bool { self.bias_id.is_some() }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genetics::{Genome, NodeGene, NodeType, ConnectionGene};
+    use crate::util::ActivationFn;
+    use super::Ctrnn;
+
+    #[test]
+    fn integrates_towards_the_input() {
+        let mut genome = Genome::new();
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Input, 0, 0.0, 0.5, ActivationFn::Identity));
+        let mut output = NodeGene::new(NodeType::Output, 1, 1.0, 0.5, ActivationFn::Identity);
+        output.set_time_constant(0.5);
+        genome.get_nodes_mut().push(output);
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(0, 2.0, true, 0, 1));
+
+        // Each Euler step of 0.1 closes a fifth of the gap to the target of 2
+        let mut network = Ctrnn::new(&genome);
+        let output = network.advance(vec![1.0], 0.1, 0.3);
+        let expected = 2.0 * (1.0 - 0.8f64.powi(3));
+        assert!((output[0] - expected).abs() < 1e-12, "{} != {}", output[0], expected);
+        assert!((network.get_time() - 0.3).abs() < 1e-12);
+
+        // A long run settles on the target
+        let output = network.advance(vec![1.0], 0.1, 10.0);
+        assert!((output[0] - 2.0).abs() < 1e-6);
+
+        network.reset();
+        assert_eq!(network.get_activation(1), None);
+        assert_eq!(network.get_time(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "total_time must be finite")]
+    fn rejects_infinite_total_time() {
+        let mut genome = Genome::new();
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Input, 0, 0.0, 0.5, ActivationFn::Identity));
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Output, 1, 1.0, 0.5, ActivationFn::Identity));
+        Ctrnn::new(&genome).advance(vec![1.0], 0.1, f64::INFINITY);
+    }
+}
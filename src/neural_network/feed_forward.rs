@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
-use crate::config::{INPUTS, BIAS, OUTPUTS};
-use crate::genetics::{Genome, Gene};
+use crate::genetics::{Genome, Gene, NodeType};
 use super::node::Node;
 
 /// A basic Feedforward neural network with no recurrency.
 pub struct FeedForwardNetwork {
-    nodes: HashMap<usize, Node> // innovation_number -> Node. Connections are stored in their 'to' Nodes
+    nodes: HashMap<usize, Node>, // innovation_number -> Node. Connections are stored in their 'to' Nodes
+    input_ids: Vec<usize>,
+    bias_id: Option<usize>,
+    output_ids: Vec<usize>,
 }
 
 impl FeedForwardNetwork {
@@ -16,11 +18,21 @@ impl FeedForwardNetwork {
 
         let mut network = FeedForwardNetwork {
             nodes:HashMap::new(),
+            input_ids: Vec::new(),
+            bias_id: None,
+            output_ids: Vec::new(),
         };
 
-        // Generate Nodes for each node_gene in the genome
+        // Generate Nodes for each node_gene in the genome, remembering which are inputs, bias & outputs.
+        // Node genes are sorted by innovation number, so input and output ids keep their order.
         for node_gene in genome.get_nodes().iter() {
-            network.nodes.insert(node_gene.get_innov(), Node::new());
+            network.nodes.insert(node_gene.get_innov(), Node::new(node_gene.get_activation(), node_gene.get_aggregation(), node_gene.get_bias(), node_gene.get_response()));
+            match node_gene.get_nodetype() {
+                NodeType::Input => network.input_ids.push(node_gene.get_innov()),
+                NodeType::Bias => network.bias_id = Some(node_gene.get_innov()),
+                NodeType::Output => network.output_ids.push(node_gene.get_innov()),
+                NodeType::Hidden => (),
+            }
         }
 
         // Inform each node which node's are it's inputs, and with what weights
@@ -45,19 +57,21 @@ impl FeedForwardNetwork {
         }
 
         // Otherwise we have to calculate it. We will set the node's activation for later quick use.
-        let mut new_activation = 0.0;
         let input_node_ids = self.nodes.get(&node_id).unwrap().get_inputs_node_ids().clone();
         let weights = self.nodes.get(&node_id).unwrap().get_weights().clone();
+        let mut weighted_inputs = Vec::with_capacity(input_node_ids.len());
 
         // RECURSIVE: for each input, check if it has an activation. If not evaluate that node first, then retrieve it's activation
         for i in 0..input_node_ids.len() {
             let input_node_id = input_node_ids[i];
             let input_activation = self.evaluate_node(input_node_id);
-            new_activation += input_activation * weights[i];
+            weighted_inputs.push(input_activation * weights[i]);
         }
 
-        // Apply sigmoid activation function
-        new_activation = 1.0 / (1.0 + (-new_activation).exp());
+        // Aggregate the weighted inputs, scale by the node's response, add its bias & apply its activation function
+        let node = self.nodes.get(&node_id).unwrap();
+        let aggregate = node.get_aggregation_fn().apply(&weighted_inputs);
+        let new_activation = node.get_activation_fn().apply(node.get_bias() + node.get_response() * aggregate);
         self.nodes.get_mut(&node_id).unwrap().set_activation(Some(new_activation));
 
         new_activation
@@ -70,33 +84,63 @@ impl FeedForwardNetwork {
     }
     
     /// feeds a supplied input into the net and returns the calculated output.
-    pub fn activate(&mut self, inputs:Vec<f64>) -> [f64;OUTPUTS] {
+    pub fn activate(&mut self, inputs:Vec<f64>) -> Vec<f64> {
         
         //Ensure no residual information remains from previous activations of network.
         self.clear_activations();
-        if inputs.len() != INPUTS {
+        if inputs.len() != self.input_ids.len() {
             panic!("FFNN received input of incorrect length");
         }
 
-        let mut output = [0.0;OUTPUTS];
+        let mut output = vec![0.0;self.output_ids.len()];
 
         // Set input nodes activation to input
-        for i in 0..INPUTS {
-            self.nodes.get_mut(&i).unwrap().set_activation(Some(inputs[i]));
+        for i in 0..self.input_ids.len() {
+            self.nodes.get_mut(&self.input_ids[i]).unwrap().set_activation(Some(inputs[i]));
         }
 
         // Set bias activation to -1 (bias to each node is manipulated by it's corresponding weight)
-        if BIAS {
-            self.nodes.get_mut(&INPUTS).unwrap().set_activation(Some(-1.0));
+        if let Some(bias_id) = self.bias_id {
+            self.nodes.get_mut(&bias_id).unwrap().set_activation(Some(-1.0));
         }
 
         // Evaluate each output node, which recursively evaluates dependent nodes on previous hidden and input "layers"
-        for i in 0..OUTPUTS {
-            output[i] = self.evaluate_node(INPUTS + BIAS as usize + i);
+        for i in 0..self.output_ids.len() {
+            output[i] = self.evaluate_node(self.output_ids[i]);
         }
         //println!("input: {:?} output: {:?}", inputs, output);
         
         output
     }
 
-}
\ No newline at end of file
+    /// Returns the activation a node reached during the last call to activate(), or None if the outputs never depended on it.
+    pub fn get_activation(&self, node_id:usize) -> Option<f64> {
+        self.nodes.get(&node_id).and_then(|node| *node.get_activation())
+    }
+
+    pub fn num_inputs(&self) -> usize { self.input_ids.len() }
+    pub fn num_outputs(&self) -> usize { self.output_ids.len() }
+    pub fn has_bias(&self) -> bool { self.bias_id.is_some() }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genetics::{Genome, NodeGene, NodeType, ConnectionGene};
+    use crate::util::ActivationFn;
+    use super::FeedForwardNetwork;
+
+    #[test]
+    fn applies_node_bias_and_response() {
+        let mut genome = Genome::new();
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Input, 0, 0.0, 0.5, ActivationFn::Identity));
+        let mut output = NodeGene::new(NodeType::Output, 1, 1.0, 0.5, ActivationFn::Identity);
+        output.set_bias(2.0);
+        output.set_response(0.5);
+        genome.get_nodes_mut().push(output);
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(0, 3.0, true, 0, 1));
+
+        let mut network = FeedForwardNetwork::new(&genome);
+        assert_eq!(network.activate(vec![4.0]), vec![2.0 + 0.5 * 12.0]);
+    }
+}
@@ -1,4 +1,10 @@
 mod feed_forward;
+mod recurrent;
+mod ctrnn;
+mod compiled;
 mod node;
 pub use feed_forward::FeedForwardNetwork;
+pub use recurrent::RecurrentNetwork;
+pub use ctrnn::Ctrnn;
+pub use compiled::CompiledNetwork;
 pub use node::Node;
@@ -1,5 +1,11 @@
+use crate::util::{ActivationFn, AggregationFn};
+
 /// A node of a neural network which contains IDs of the node which are inputs to itself. 
 pub struct Node {
+    activation_fn: ActivationFn,
+    aggregation_fn: AggregationFn,
+    bias: f64,
+    response: f64,
     activation: Option<f64>,
     input_node_ids: Vec<usize>, // (node_id, weight)
     weights: Vec<f64>,
@@ -8,8 +14,12 @@ pub struct Node {
 impl Node {
 
     /// Creates a new node with empty input_node_ids, weights and a 'None' activation
-    pub fn new() -> Self {
+    pub fn new(activation_fn:ActivationFn, aggregation_fn:AggregationFn, bias:f64, response:f64) -> Self {
         Self {
+            activation_fn,
+            aggregation_fn,
+            bias,
+            response,
             activation: None,
             input_node_ids: Vec::new(),
             weights: Vec::new(),
@@ -25,6 +35,10 @@ impl Node {
         }
     }
 
+    pub fn get_activation_fn(&self) -> ActivationFn { self.activation_fn }
+    pub fn get_aggregation_fn(&self) -> AggregationFn { self.aggregation_fn }
+    pub fn get_bias(&self) -> f64 { self.bias }
+    pub fn get_response(&self) -> f64 { self.response }
     pub fn get_activation(&self) -> &Option<f64> { &self.activation }
     pub fn get_inputs_node_ids(&self) -> &Vec<usize> { &self.input_node_ids }
     pub fn get_weights(&self) -> &Vec<f64> { &self.weights }
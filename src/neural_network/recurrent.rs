@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::genetics::{Genome, Gene, NodeType};
+use super::node::Node;
+
+/// A recurrent neural network, which may contain back edges & self loops.
+/// Every node is updated at once from the activations of the previous step, so activations persist between calls to activate().
+pub struct RecurrentNetwork {
+    nodes: HashMap<usize, Node>, // innovation_number -> Node. Connections are stored in their 'to' Nodes
+    update_ids: Vec<usize>, // hidden & output nodes, sorted so they are always updated in the same order
+    input_ids: Vec<usize>,
+    bias_id: Option<usize>,
+    output_ids: Vec<usize>,
+}
+
+impl RecurrentNetwork {
+
+    /// Constructs a new recurrent network from a given genome, with every activation starting at 0.
+    pub fn new(genome:&Genome) -> RecurrentNetwork {
+
+        let mut network = RecurrentNetwork {
+            nodes: HashMap::new(),
+            update_ids: Vec::new(),
+            input_ids: Vec::new(),
+            bias_id: None,
+            output_ids: Vec::new(),
+        };
+
+        for node_gene in genome.get_nodes().iter() {
+            network.nodes.insert(node_gene.get_innov(), Node::new(node_gene.get_activation(), node_gene.get_aggregation(), node_gene.get_bias(), node_gene.get_response()));
+            match node_gene.get_nodetype() {
+                NodeType::Input => network.input_ids.push(node_gene.get_innov()),
+                NodeType::Bias => network.bias_id = Some(node_gene.get_innov()),
+                NodeType::Output => {
+                    network.output_ids.push(node_gene.get_innov());
+                    network.update_ids.push(node_gene.get_innov());
+                }
+                NodeType::Hidden => network.update_ids.push(node_gene.get_innov()),
+            }
+        }
+
+        for conn_gene in genome.get_connections().iter() {
+            if conn_gene.is_enabled() {
+                let to = network.nodes.get_mut(&conn_gene.get_to()).unwrap();
+                to.push(conn_gene.get_from(), conn_gene.get_weight());
+            }
+        }
+
+        network
+    }
+
+    /// Advances the network by one step with the supplied input and returns the new output.
+    /// Each node reads the activations its inputs reached on the previous step, so a signal takes one step per connection to travel.
+    pub fn activate(&mut self, inputs:Vec<f64>) -> Vec<f64> {
+        if inputs.len() != self.input_ids.len() {
+            panic!("RecurrentNetwork received input of incorrect length");
+        }
+
+        for (id, input) in self.input_ids.iter().zip(inputs.iter()) {
+            self.nodes.get_mut(id).unwrap().set_activation(Some(*input));
+        }
+        if let Some(bias_id) = self.bias_id {
+            self.nodes.get_mut(&bias_id).unwrap().set_activation(Some(-1.0));
+        }
+
+        // Snapshot the previous step, so the order nodes are updated in does not matter
+        let previous: HashMap<usize, f64> = self.nodes.iter()
+            .map(|(id, node)| (*id, node.get_activation().unwrap_or(0.0)))
+            .collect();
+
+        for id in self.update_ids.iter() {
+            let node = self.nodes.get(id).unwrap();
+            let weighted_inputs: Vec<f64> = node.get_inputs_node_ids().iter()
+                .zip(node.get_weights().iter())
+                .map(|(input_id, weight)| previous[input_id] * weight)
+                .collect();
+            let aggregate = node.get_aggregation_fn().apply(&weighted_inputs);
+            let new_activation = node.get_activation_fn().apply(node.get_bias() + node.get_response() * aggregate);
+            self.nodes.get_mut(id).unwrap().set_activation(Some(new_activation));
+        }
+
+        self.output_ids.iter().map(|id| self.get_activation(*id).unwrap()).collect()
+    }
+
+    /// Clears the state held between calls to activate(), returning every activation to 0.
+    pub fn reset(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.set_activation(None);
+        }
+    }
+
+    /// Returns the activation a node reached on the last step, or None if the network has not been activated since it was reset.
+    pub fn get_activation(&self, node_id:usize) -> Option<f64> {
+        self.nodes.get(&node_id).and_then(|node| *node.get_activation())
+    }
+
+    pub fn num_inputs(&self) -> usize { self.input_ids.len() }
+    pub fn num_outputs(&self) -> usize { self.output_ids.len() }
+    pub fn has_bias(&self) -> bool { self.bias_id.is_some() }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Neat, NeatConfig, Termination};
+    use crate::genetics::{Genome, NodeGene, NodeType, ConnectionGene};
+    use crate::util::ActivationFn;
+    use super::RecurrentNetwork;
+
+    // Rewards echoing the first input of a sequence at every later step. The signal is gone from the inputs
+    // long before the sequence ends, so only a network with a loop can remember it.
+    fn echo(genome:&Genome) -> f64 {
+        let mut network = RecurrentNetwork::new(genome);
+        let mut error = 0.0;
+        for first in [0.0, 1.0] {
+            network.reset();
+            for step in 0..12 {
+                let output = network.activate(vec![if step == 0 { first } else { 0.0 }]);
+                if step >= 3 {
+                    error += (first - output[0]).abs();
+                }
+            }
+        }
+        18.0 - error
+    }
+
+    fn has_loop(genome:&Genome) -> bool {
+        genome.get_connections().iter().filter(|conn| conn.is_enabled()).any(|conn| {
+            let (from, to) = (genome.get_nodes().get_by_innov(conn.get_from()), genome.get_nodes().get_by_innov(conn.get_to()));
+            from.get_x() >= to.get_x()
+        })
+    }
+
+    #[test]
+    fn self_loop_accumulates_until_reset() {
+        let mut genome = Genome::new();
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Input, 0, 0.0, 0.5, ActivationFn::Identity));
+        genome.get_nodes_mut().push(NodeGene::new(NodeType::Output, 1, 1.0, 0.5, ActivationFn::Identity));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(0, 1.0, true, 0, 1));
+        genome.get_connections_mut().push(ConnectionGene::new_explicit(1, 1.0, true, 1, 1));
+
+        let mut network = RecurrentNetwork::new(&genome);
+        assert_eq!(network.activate(vec![2.0]), vec![2.0]);
+        assert_eq!(network.activate(vec![3.0]), vec![5.0]);
+        assert_eq!(network.activate(vec![0.0]), vec![5.0]);
+        network.reset();
+        assert_eq!(network.get_activation(1), None);
+        assert_eq!(network.activate(vec![1.0]), vec![1.0]);
+    }
+
+    fn train_echo(recurrent:bool) -> (Genome, NeatConfig) {
+        let mut config = NeatConfig::new(1, 1);
+        config.population_size = 100;
+        config.recurrent = recurrent;
+        config.validate_genomes = true;
+        config.m_node = 0.2;
+        config.m_conn = 0.5;
+        config.seed = Some(3);
+        let mut neat = Neat::new_fully_connected(config);
+        let result = neat.train_until(echo, &Termination { max_generations: Some(30), ..Termination::default() });
+        (result.champion, neat.get_config().clone())
+    }
+
+    #[test]
+    fn trains_recurrent_genomes() {
+        let (champion, config) = train_echo(true);
+        assert!(champion.validate(&config).is_empty());
+        assert!(has_loop(&champion));
+
+        let (baseline, _) = train_echo(false);
+        assert!(!has_loop(&baseline));
+        assert!(echo(&champion) > echo(&baseline) + 1.0, "{} vs {}", echo(&champion), echo(&baseline));
+    }
+}
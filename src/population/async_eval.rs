@@ -0,0 +1,144 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::stream::{self, StreamExt};
+use rand::SeedableRng;
+
+use crate::genetics::Genome;
+use crate::util::{NeatRng, swap_eval_rng};
+use super::FailureReason;
+use super::evaluator::panic_message;
+
+/// Returns the fitness of each genome, in order, awaiting up to max_in_flight evaluations at once.
+/// Genome i is evaluated with its eval rng seeded with seeds[i], and a panic only fails the genome being evaluated.
+pub(crate) async fn evaluate_async<F>(organisms:&[Genome], env:&F, seeds:&[u64], max_in_flight:usize) -> Vec<Result<f64, FailureReason>>
+where F: AsyncFn(&Genome) -> f64 {
+    assert_eq!(organisms.len(), seeds.len());
+    stream::iter(organisms.iter().zip(seeds.iter()))
+        .map(|(genome, seed)| Evaluation { future: Box::pin(env(genome)), rng: NeatRng::seed_from_u64(*seed) })
+        .buffered(max_in_flight)
+        .collect()
+        .await
+}
+
+/// A single genome's evaluation, which swaps its own eval rng in for each poll,
+/// so interleaved evaluations draw the same numbers as they would one at a time.
+struct Evaluation<Fut> {
+    future: Pin<Box<Fut>>,
+    rng: NeatRng,
+}
+
+impl<Fut: Future<Output = f64>> Future for Evaluation<Fut> {
+    type Output = Result<f64, FailureReason>;
+
+    fn poll(self: Pin<&mut Self>, cx:&mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        swap_eval_rng(&mut this.rng);
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| this.future.as_mut().poll(cx)));
+        swap_eval_rng(&mut this.rng);
+        match poll {
+            Ok(Poll::Ready(fitness)) => Poll::Ready(Ok(fitness)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(FailureReason::Panicked(panic_message(payload)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::future::poll_fn;
+    use std::sync::Arc;
+    use std::task::Poll;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+    use rand::Rng;
+    use crate::{Neat, NeatConfig, FitnessFn, Evaluator, FailureReason, Genome, with_eval_rng};
+    use crate::test_environments::xor;
+    use super::evaluate_async;
+
+    /// Returns to the executor once, as a fitness function awaiting a simulator would.
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }).await
+    }
+
+    async fn noisy_xor(genome:&Genome) -> f64 {
+        let noise: f64 = with_eval_rng(|rng| rng.gen_range(0.0..0.1));
+        yield_now().await;
+        xor(genome) + noise + with_eval_rng(|rng| rng.gen_range(0.0..0.1))
+    }
+
+    /// noisy_xor without the await, for comparison.
+    fn threaded_noisy_xor() -> FitnessFn {
+        Arc::new(|genome| {
+            let noise: f64 = with_eval_rng(|rng| rng.gen_range(0.0..0.1));
+            xor(genome) + noise + with_eval_rng(|rng| rng.gen_range(0.0..0.1))
+        })
+    }
+
+    #[test]
+    fn bounded_and_matches_threaded_evaluation() {
+        let organisms: Vec<Genome> = (0..20).map(|_| Genome::new_init_xor()).collect();
+        let seeds: Vec<u64> = (0..20).collect();
+        let (in_flight, most_in_flight) = (Cell::new(0), Cell::new(0));
+        let env = async |genome:&Genome| {
+            in_flight.set(in_flight.get() + 1);
+            most_in_flight.set(most_in_flight.get().max(in_flight.get()));
+            let fitness = noisy_xor(genome).await;
+            in_flight.set(in_flight.get() - 1);
+            fitness
+        };
+
+        let results = block_on(evaluate_async(&organisms, &env, &seeds, 4));
+        assert_eq!(most_in_flight.get(), 4);
+        assert_eq!(results, Evaluator::new(2, 1).evaluate(&organisms, &threaded_noisy_xor(), &seeds, None));
+
+        let panicking = async |_genome:&Genome| -> f64 { yield_now().await; panic!("simulator disconnected") };
+        let results = block_on(evaluate_async(&organisms[..2], &panicking, &seeds[..2], 4));
+        assert_eq!(results[0], Err(FailureReason::Panicked(String::from("simulator disconnected"))));
+    }
+
+    #[test]
+    fn step_async_matches_step() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.seed = Some(9);
+        let (mut a, mut b) = (Neat::new_fully_connected(config.clone()), Neat::new_fully_connected(config));
+        let env = threaded_noisy_xor();
+        for _ in 0..3 {
+            let (sync_report, async_report) = (a.step(&env), block_on(b.step_async(&noisy_xor)));
+            assert_eq!(sync_report.mean_fitness, async_report.mean_fitness);
+        }
+        assert_eq!(a.get_population().get_fitness_vec(), b.get_population().get_fitness_vec());
+    }
+
+    #[test]
+    fn cancelled_step_changes_nothing() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        config.seed = Some(4);
+        let (mut cancelled, mut uncancelled) = (Neat::new_fully_connected(config.clone()), Neat::new_fully_connected(config));
+        let env = threaded_noisy_xor();
+        cancelled.step(&env);
+        uncancelled.step(&env);
+
+        // Polled once, the step reproduces & mutates, then is dropped while evaluating
+        assert!(cancelled.step_async(&noisy_xor).now_or_never().is_none());
+        let (expected, actual) = (uncancelled.step(&env), cancelled.step(&env));
+        assert_eq!(expected.generation, actual.generation);
+        assert_eq!(uncancelled.get_population().get_fitness_vec(), cancelled.get_population().get_fitness_vec());
+        assert_eq!(
+            bincode::serialize(&*uncancelled.get_population().organisms.read().unwrap()).unwrap(),
+            bincode::serialize(&*cancelled.get_population().organisms.read().unwrap()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::genetics::Genome;
+use crate::util::seed_eval_rng;
+use super::FitnessFn;
+
+/// Why a genome could not be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureReason {
+    Panicked(String), // the fitness function panicked with this message
+    TimedOut(Duration), // the fitness function ran for longer than this limit
+    Crashed(String), // the worker process evaluating the genome died, for this reason
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::Panicked(message) => write!(f, "fitness function panicked: {}", message),
+            FailureReason::TimedOut(limit) => write!(f, "fitness function ran for longer than {:.2?}", limit),
+            FailureReason::Crashed(reason) => write!(f, "worker process crashed: {}", reason),
+        }
+    }
+}
+
+/// A genome which could not be evaluated, and so was given config.failed_fitness.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalFailure {
+    pub genome_id: usize,
+    pub reason: FailureReason,
+}
+
+/// A persistent pool of worker threads which evaluate genomes in parallel.
+/// Genomes are split into chunks which idle workers steal from busy ones, and each fitness is written straight into its own slot of the result.
+/// Clones share the same threads.
+#[derive(Clone)]
+pub struct Evaluator {
+    pool: Arc<ThreadPool>,
+    chunk_size: usize, // the fewest genomes a worker evaluates in one job
+}
+
+impl Evaluator {
+
+    /// Spawns num_threads worker threads, which live until the Evaluator is dropped.
+    pub fn new(num_threads:usize, chunk_size:usize) -> Self {
+        assert!(num_threads != 0);
+        assert!(chunk_size != 0);
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("neat-eval-{}", i))
+            .build()
+            .expect("failed to spawn evaluation threads");
+        Self { pool: Arc::new(pool), chunk_size }
+    }
+
+    /// Returns the fitness of each genome, in order. Genome i is evaluated with its eval rng seeded with seeds[i].
+    /// A panic in the fitness function only fails the genome being evaluated. With a timeout, a genome whose evaluation
+    /// takes longer fails too. Evaluations cannot be interrupted, so one which times out is left running on its own thread.
+    pub fn evaluate(&self, organisms:&[Genome], env:&FitnessFn, seeds:&[u64], timeout:Option<Duration>) -> Vec<Result<f64, FailureReason>> {
+        assert_eq!(organisms.len(), seeds.len());
+        self.pool.install(|| {
+            organisms.par_iter()
+                .zip(seeds.par_iter())
+                .with_min_len(self.chunk_size)
+                .map(|(genome, seed)| match timeout {
+                    None => evaluate_caught(genome, env, *seed),
+                    Some(limit) => evaluate_timed(genome, env, *seed, limit),
+                })
+                .collect()
+        })
+    }
+
+    /// Runs op on the worker threads, so any parallel iterators within it share them.
+    pub fn install<OP, R>(&self, op:OP) -> R where OP: FnOnce() -> R + Send, R: Send {
+        self.pool.install(op)
+    }
+
+    pub fn num_threads(&self) -> usize { self.pool.current_num_threads() }
+    pub fn get_chunk_size(&self) -> usize { self.chunk_size }
+}
+
+fn evaluate_caught(genome:&Genome, env:&FitnessFn, seed:u64) -> Result<f64, FailureReason> {
+    seed_eval_rng(seed);
+    panic::catch_unwind(AssertUnwindSafe(|| env(genome))).map_err(|payload| FailureReason::Panicked(panic_message(payload)))
+}
+
+/// Evaluates on a new thread, so the worker can give up waiting once limit has passed.
+fn evaluate_timed(genome:&Genome, env:&FitnessFn, seed:u64, limit:Duration) -> Result<f64, FailureReason> {
+    let (sender, receiver) = mpsc::channel();
+    let (genome, env) = (genome.clone(), Arc::clone(env));
+    thread::Builder::new()
+        .name(String::from("neat-eval-timed"))
+        .spawn(move || { let _ = sender.send(evaluate_caught(&genome, &env, seed)); })
+        .expect("failed to spawn evaluation thread");
+    match receiver.recv_timeout(limit) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(FailureReason::TimedOut(limit)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(FailureReason::Panicked(String::from("evaluation thread exited without a result"))),
+    }
+}
+
+pub(super) fn panic_message(payload:Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::genetics::Genome;
+    use crate::population::FitnessFn;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use crate::util::with_eval_rng;
+    use rand::Rng;
+    use super::{Evaluator, FailureReason};
+
+    #[test]
+    fn results_do_not_depend_on_threads() {
+        let organisms: Vec<Genome> = (0..100).map(|_| Genome::new_init_xor()).collect();
+        let seeds: Vec<u64> = (0..100).collect();
+        let env: FitnessFn = Arc::new(|_genome| with_eval_rng(|rng| rng.gen::<f64>()));
+
+        let single = Evaluator::new(1, 1).evaluate(&organisms, &env, &seeds, None);
+        let evaluator = Evaluator::new(4, 7);
+        assert_eq!(single, evaluator.evaluate(&organisms, &env, &seeds, None));
+        // Workers persist between calls, and timed evaluations are seeded the same way
+        assert_eq!(single, evaluator.evaluate(&organisms, &env, &seeds, Some(Duration::from_secs(10))));
+    }
+
+    #[test]
+    fn isolates_panics_and_timeouts() {
+        let organisms: Vec<Genome> = (0..3).map(|_| Genome::new_init_xor()).collect();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let env: FitnessFn = Arc::new(move |_genome| match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => panic!("bad genome"),
+            1 => { thread::sleep(Duration::from_millis(500)); 1.0 }
+            _ => 2.0,
+        });
+
+        let results = Evaluator::new(1, 1).evaluate(&organisms, &env, &[0, 1, 2], Some(Duration::from_millis(50)));
+        assert_eq!(results[0], Err(FailureReason::Panicked(String::from("bad genome"))));
+        assert_eq!(results[1], Err(FailureReason::TimedOut(Duration::from_millis(50))));
+        assert_eq!(results[2], Ok(2.0));
+    }
+}
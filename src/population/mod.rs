@@ -1,3 +1,9 @@
 mod population;
+mod evaluator;
+mod workers;
+mod async_eval;
 
-pub use population::Population;
\ No newline at end of file
+pub use population::{Population, FitnessFn};
+pub(crate) use population::PopulationSnapshot;
+pub use evaluator::{Evaluator, EvalFailure, FailureReason};
+pub use workers::{WorkerPool, run_worker, is_worker, WORKER_ADDR_VAR, WORKER_TOKEN_VAR};
@@ -1,18 +1,71 @@
 use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
 
-use crate::config::{POPULATION_SIZE, TARGET_SPECIES_NUM, COMPATABILITY_MODIFIER, COMPATABILITY_THRESHOLD, COMPATABILITY_MIN, ELITISM, NUM_THREADS};
+use crate::config::NeatConfig;
 use crate::genetics::{NodeGene, ConnectionGene, Genome, distance, crossover};
 use crate::speciation::{Species};
 
-use crate::util::ThreadPool;
-use std::sync::{Arc, Mutex, RwLock};
+use crate::util::NeatRng;
+use super::{Evaluator, EvalFailure, FailureReason, WorkerPool};
+use super::async_eval::evaluate_async;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How a child of the next generation is made.
+enum Offspring {
+    Elite(usize), // an unmutated copy of this genome
+    Child(usize, usize, u64), // a crossover of two parents, using an rng with this seed
+}
+
+/// Genomes moved out of their lock during async evaluation, which are put back when dropped,
+/// so the population is restored even if the evaluation future is cancelled.
+struct TakenOrganisms<'a> {
+    lock: &'a RwLock<Vec<Genome>>,
+    organisms: Vec<Genome>,
+}
+
+impl<'a> TakenOrganisms<'a> {
+    fn new(lock:&'a RwLock<Vec<Genome>>) -> Self {
+        let organisms = std::mem::take(&mut *lock.write().unwrap());
+        Self { lock, organisms }
+    }
+}
+
+impl Drop for TakenOrganisms<'_> {
+    fn drop(&mut self) {
+        *self.lock.write().unwrap() = std::mem::take(&mut self.organisms);
+    }
+}
+
+/// The parts of a population which change before a generation is evaluated.
+/// Reproduction replaces the organisms rather than modifying them, so the parents are kept without copying them.
+pub(crate) struct PopulationSnapshot {
+    organisms: Arc<RwLock<Vec<Genome>>>,
+    to_mutate: Vec<bool>,
+    generation: usize,
+    gens_stagnated: usize,
+}
+
+/// A fitness function shared between the evaluation threads.
+/// Any closure which is Send + Sync can be used, so fitness functions may capture datasets, seeds or simulator configs.
+pub type FitnessFn = Arc<dyn Fn(&Genome) -> f64 + Send + Sync>;
 
 /// The container for all the genomes, generational data and population-wide functions.
+#[derive(Serialize, Deserialize)]
 pub struct Population {
+    config: NeatConfig,
     champion: Option<Genome>,
     pub organisms: Arc<RwLock<Vec<Genome>>>,
-    to_mutate: [bool;POPULATION_SIZE], // prevents genomes reproduced via elitism from being mutated.
-    fitness_arr: Arc<Mutex<[f64;POPULATION_SIZE]>>,
+    to_mutate: Vec<bool>, // prevents genomes reproduced via elitism from being mutated.
+    fitness_arr: Vec<f64>,
+    #[serde(skip)]
+    evaluator: Option<Evaluator>, // started on the first evaluation and kept for every generation after
+    #[serde(skip)]
+    failures: Vec<EvalFailure>, // genomes of the latest generation which could not be evaluated
+    #[serde(skip)]
+    workers: Option<WorkerPool>, // when set, genomes are evaluated in these worker processes instead of by the evaluator
     max_fitness: f64,
 
     species_vec: Vec<Species>,
@@ -21,80 +74,107 @@ pub struct Population {
     species_id_counter: usize,
     generation: usize,
     gens_stagnated: usize,
+    evaluations: usize,
+
+    // Events of the latest generation, for reporting
+    new_champion: bool,
+    created_species: Vec<usize>,
+    extinct_species: Vec<usize>,
+    culled_species: Vec<usize>,
 }
 
 impl Population {
     /// Creates a new population of initialised genomes.
-    pub fn new(node_pool:&HashMap<usize, NodeGene>) -> Self {
-        Self {
-
-            organisms: 
-            {
-                let v = Arc::new(RwLock::new(Vec::new()));
-                let mut v_writer = v.write().unwrap();
-                for _i in 0..POPULATION_SIZE {
-                    v_writer.push(Genome::new_init(node_pool));
-                }
-                drop(v_writer);
-                v
-            },
-            to_mutate: [true;POPULATION_SIZE],
-            fitness_arr: Arc::new(Mutex::new([0.0;POPULATION_SIZE])),
-            max_fitness: 0.0,
-            champion: None,
-
-            species_vec: Vec::new(),
-            compatability_threshold: COMPATABILITY_THRESHOLD,
-            pop_avg_adj_fit: 0.0,
-            species_id_counter: 0,
-            generation: 0,
-            gens_stagnated: 0,
-        }
+    pub fn new(node_pool:&HashMap<usize, NodeGene>, config:&NeatConfig) -> Self {
+        let organisms = (0..config.population_size).map(|_| Genome::new_init(node_pool, config)).collect();
+        Self::with_organisms(organisms, config)
     }
-    pub fn new_fully_connected(node_pool:&HashMap<usize, NodeGene>, conn_pool:&HashMap<usize, ConnectionGene>) -> Self {
+    pub fn new_fully_connected(node_pool:&HashMap<usize, NodeGene>, conn_pool:&HashMap<usize, ConnectionGene>, config:&NeatConfig) -> Self {
+        let organisms = (0..config.population_size).map(|_| Genome::new_fully_connected(node_pool, conn_pool, config)).collect();
+        Self::with_organisms(organisms, config)
+    }
+
+    /// A population of the given genomes, before any have been evaluated.
+    fn with_organisms(organisms:Vec<Genome>, config:&NeatConfig) -> Self {
         Self {
+            config: config.clone(),
 
-            organisms: 
-            {
-                let mut v = Arc::new(RwLock::new(Vec::new()));
-                let mut v_writer = v.write().unwrap();
-                for _i in 0..POPULATION_SIZE {
-                    v_writer.push(Genome::new_fully_connected(node_pool, conn_pool));
-                }
-                drop(v_writer);
-                v
-            },
-            to_mutate: [true;POPULATION_SIZE],
-            fitness_arr: Arc::new(Mutex::new([0.0;POPULATION_SIZE])),
+            organisms: Arc::new(RwLock::new(organisms)),
+            to_mutate: vec![true;config.population_size],
+            fitness_arr: vec![0.0;config.population_size],
+            evaluator: None,
+            failures: Vec::new(),
+            workers: None,
             max_fitness: 0.0,
             champion: None,
 
             species_vec: Vec::new(),
-            compatability_threshold: COMPATABILITY_THRESHOLD,
+            compatability_threshold: config.compatability_threshold,
             pop_avg_adj_fit: 0.0,
             species_id_counter: 0,
             generation: 0,
             gens_stagnated: 0,
+            evaluations: 0,
+
+            new_champion: false,
+            created_species: Vec::new(),
+            extinct_species: Vec::new(),
+            culled_species: Vec::new(),
         }
     }
 
     /// Runs each genome through the supplied environment.
     /// Updates the champion genome if a better one is found.
-    pub fn calculate_fitnesses(&mut self, env:for<'r> fn(&'r Genome) -> f64 ) {
+    /// Each genome is evaluated with its own eval rng stream, seeded from rng.
+    /// Genomes whose fitness function panics or exceeds config.eval_timeout are given config.failed_fitness.
+    /// With a WorkerPool set, env is not used, as the workers evaluate with their own fitness function.
+    pub fn calculate_fitnesses(&mut self, env:&FitnessFn, rng:&mut NeatRng) {
         self.gens_stagnated += 1;
 
-        let pool = ThreadPool::new(NUM_THREADS, &self.fitness_arr, &self.organisms);
-        for i in 0..POPULATION_SIZE {
-            pool.execute(i, env);
-        }
-        drop(pool);
-        let fitnesses = self.fitness_arr.lock().unwrap();
+        let seeds: Vec<u64> = (0..self.config.population_size).map(|_| rng.gen()).collect();
+        let timeout = self.config.eval_timeout.map(Duration::from_secs_f64);
+        let results = match &self.workers {
+            Some(workers) => workers.evaluate(&self.organisms.read().unwrap(), &seeds, timeout),
+            None => {
+                let evaluator = self.get_evaluator().clone();
+                evaluator.evaluate(&self.organisms.read().unwrap(), env, &seeds, timeout)
+            }
+        };
+        self.record_fitnesses(results);
+    }
+
+    /// Same as calculate_fitnesses, but awaits an async fitness function for up to config.max_concurrent_evals genomes at once.
+    /// Evaluations are interleaved on the calling task rather than given a thread each, so config.eval_timeout is not applied
+    /// and any WorkerPool is not used. Each evaluation keeps its own eval rng stream, so results match calculate_fitnesses.
+    pub async fn calculate_fitnesses_async<F>(&mut self, env:&F, rng:&mut NeatRng) where F: AsyncFn(&Genome) -> f64 {
+        self.gens_stagnated += 1;
+
+        let seeds: Vec<u64> = (0..self.config.population_size).map(|_| rng.gen()).collect();
+        // Taken out so the lock is not held across awaits
+        let taken = TakenOrganisms::new(&self.organisms);
+        let results = evaluate_async(&taken.organisms, env, &seeds, self.config.max_concurrent_evals).await;
+        drop(taken);
+        self.record_fitnesses(results);
+    }
+
+    /// Stores the fitness of each genome, giving failed genomes config.failed_fitness, and updates the champion.
+    fn record_fitnesses(&mut self, results:Vec<Result<f64, FailureReason>>) {
         let organisms = self.organisms.read().unwrap();
-        for i in 0..POPULATION_SIZE {
-            if fitnesses[i] > self.max_fitness {
-                self.max_fitness = fitnesses[i];
+        self.failures.clear();
+        for (genome_id, result) in results.into_iter().enumerate() {
+            self.fitness_arr[genome_id] = result.unwrap_or_else(|reason| {
+                self.failures.push(EvalFailure { genome_id, reason });
+                self.config.failed_fitness
+            });
+        }
+        self.evaluations += self.config.population_size;
+        self.new_champion = false;
+        for i in 0..self.config.population_size {
+            if self.fitness_arr[i] > self.max_fitness || self.champion.is_none() {
+                self.max_fitness = self.fitness_arr[i];
                 self.champion = Some(organisms[i].clone());
                 self.gens_stagnated = 0;
+                self.new_champion = true;
             }
         }
     }
@@ -102,29 +182,33 @@ impl Population {
     /// Iterates over each genome in the population and places them in their most similar species.
     /// If the genome's distance from the the most similar species is too large, above the compatability threshold, then a new species is created.
     pub fn speciate(&mut self) {
+        self.created_species.clear();
+        self.extinct_species.clear();
+        self.culled_species.clear();
     
         for species in self.species_vec.iter_mut() {
             species.new_generation();
         }
     
         // Insert each genome into MOST SIMILAR species
-        for i in 0..POPULATION_SIZE {
+        for i in 0..self.config.population_size {
             let mut smallest_dist = f64::MAX;
             let mut index = usize::MAX;
             for j in 0..self.species_vec.len() { 
-                let dist = distance(&self.organisms.read().unwrap()[i], self.species_vec[j].get_rep()); 
+                let dist = distance(&self.organisms.read().unwrap()[i], self.species_vec[j].get_rep(), &self.config); 
                 if dist < smallest_dist { 
                     smallest_dist = dist;
                     index = j;
                 }
             }
             if smallest_dist < self.compatability_threshold {
-                self.species_vec[index].insert(i, self.fitness_arr.lock().unwrap()[i]);
+                self.species_vec[index].insert(i, self.fitness_arr[i], &self.config);
             }
             else {
                 let length = self.species_vec.len();
                 self.species_vec.push(Species::new(self.species_id_counter, self.organisms.read().unwrap()[i].clone()));
-                self.species_vec[length].insert(i, self.fitness_arr.lock().unwrap()[i]);
+                self.species_vec[length].insert(i, self.fitness_arr[i], &self.config);
+                self.created_species.push(self.species_id_counter);
                 self.species_id_counter += 1;
 
             }
@@ -135,6 +219,9 @@ impl Population {
         for i in 0..self.species_vec.len() {
             if self.species_vec[i].is_extinct() {
                 extinct_species_indexes.push(i);
+                self.extinct_species.push(self.species_vec[i].get_id());
+            } else if self.species_vec[i].get_gens_stagnated() > self.config.dropoff_age {
+                self.culled_species.push(self.species_vec[i].get_id());
             }
         }
         let mut iter = 0;
@@ -152,82 +239,114 @@ impl Population {
             // let median_genome = self.organisms[species.get_median_id()].clone();
             // species.set_rep(median_genome);
         }
-        self.pop_avg_adj_fit = pop_avg_adj_fit / POPULATION_SIZE as f64;
+        self.pop_avg_adj_fit = pop_avg_adj_fit / self.config.population_size as f64;
     
         // Adjust compatability_threshold to help reduce/increase number of species in next generation
-        if self.species_vec.len() > TARGET_SPECIES_NUM {
-            self.compatability_threshold += COMPATABILITY_MODIFIER;
-        } else if self.species_vec.len() < TARGET_SPECIES_NUM && self.compatability_threshold > COMPATABILITY_MIN{
-            self.compatability_threshold -= COMPATABILITY_MODIFIER;
+        if self.species_vec.len() > self.config.target_species_num {
+            self.compatability_threshold += self.config.compatability_modifier;
+        } else if self.species_vec.len() < self.config.target_species_num && self.compatability_threshold > self.config.compatability_min {
+            self.compatability_threshold -= self.config.compatability_modifier;
         }
-        println!("new CT: {}", self.compatability_threshold);
     }
 
 
     /// Creates an entirely new population via intra-species crossover.
     /// Parents are randomly selected with a probability directly proportional to their fitness.
-    pub fn reproduce(&mut self) {
+    /// Parents are chosen in order, then crossed over in parallel, each child with its own rng seeded from rng.
+    pub fn reproduce(&mut self, rng:&mut NeatRng) {
         self.generation += 1;
-        let new_pop = Arc::new(RwLock::new(Vec::<Genome>::new()));
-        let mut pop_idx = 0;
-        let mut new_pop_writer = new_pop.write().unwrap();
+        let mut offspring: Vec<Offspring> = Vec::with_capacity(self.config.population_size);
         for species in &self.species_vec {
             let allowed_offspring = species.allowed_offspring(self.pop_avg_adj_fit);
 
             for i in 0..allowed_offspring {
-                if i < ELITISM {
-                    new_pop_writer.push(self.organisms.read().unwrap()[species.get_first()].clone());
-                    self.to_mutate[pop_idx] = false;
-                    pop_idx += 1;
+                if i < self.config.elitism {
+                    offspring.push(Offspring::Elite(species.get_first()));
                 } else {
-                    let parent1_id = species.get_fit_member_id();
-                    let parent2_id = species.get_fit_member_id();
-                    let fitnesses = self.fitness_arr.lock().unwrap();
-                    let p1_fitter_than_p2: bool = fitnesses[parent1_id] > fitnesses[parent2_id];
-                    new_pop_writer.push(crossover(&self.organisms.read().unwrap()[parent1_id], &self.organisms.read().unwrap()[parent2_id], p1_fitter_than_p2));
-                    self.to_mutate[pop_idx] = true;
-                    pop_idx += 1;
+                    let parent1_id = species.get_fit_member_id(rng);
+                    let parent2_id = species.get_fit_member_id(rng);
+                    offspring.push(Offspring::Child(parent1_id, parent2_id, rng.gen()));
                 }
             }
         }
 
-        // A fill to ensure popultation stays at POPULATION_SIZE
+        let evaluator = self.get_evaluator().clone();
+        let organisms = self.organisms.read().unwrap();
+        let fitnesses = &self.fitness_arr;
+        let mut new_pop: Vec<Genome> = evaluator.install(|| offspring.par_iter().map(|child| match child {
+            Offspring::Elite(id) => organisms[*id].clone(),
+            Offspring::Child(parent1_id, parent2_id, seed) => {
+                let p1_fitter_than_p2: bool = fitnesses[*parent1_id] > fitnesses[*parent2_id];
+                crossover(&organisms[*parent1_id], &organisms[*parent2_id], p1_fitter_than_p2, &mut NeatRng::seed_from_u64(*seed))
+            }
+        }).collect());
+        drop(organisms);
+
+        for (pop_idx, child) in offspring.iter().enumerate() {
+            self.to_mutate[pop_idx] = !matches!(child, Offspring::Elite(_));
+        }
+        let mut pop_idx = offspring.len();
+
+        // A fill to ensure popultation stays at population_size
         // MIGHT not be necessary
-        while new_pop_writer.len() < POPULATION_SIZE {
+        while new_pop.len() < self.config.population_size {
             if let Some(g) = &self.champion {
-                new_pop_writer.push(g.clone());
+                new_pop.push(g.clone());
                 self.to_mutate[pop_idx] = true;
                 pop_idx += 1;
             } else {
                 panic!("Champion not found whent attempting to fill incomplete population.")
             }
         }
-        drop(new_pop_writer);
-        self.organisms = new_pop;
+        self.organisms = Arc::new(RwLock::new(new_pop));
     }
 
-    /// Prints analysis data to the console, to help the user track the progress.
-    pub fn generation_info(&self) {
-        // print generation number
-        // print generation best fitness
-        // print generation mean fitness
-        // print each species, ID, num_members, best_fitness
-        println!("===== Generation {} ======", self.generation);
-        println!("  ID    Num Members      Mean Fitness       Best Fitness      Gens Stagnant");
-        println!("Pop,          {POPULATION_SIZE},          {:.2},         {:.2}              {}",  self.get_mean_fitness(), self.max_fitness, self.gens_stagnated);
-        for species in self.species_vec.iter() {
-            println!("{}               {}           {:.2}             {:.2}           {}", species.get_id(), species.len(), species.get_mean_fitness(), species.get_best_fitness(), species.get_gens_stagnated());
+    pub(crate) fn snapshot(&self) -> PopulationSnapshot {
+        PopulationSnapshot {
+            organisms: Arc::clone(&self.organisms),
+            to_mutate: self.to_mutate.clone(),
+            generation: self.generation,
+            gens_stagnated: self.gens_stagnated,
         }
+    }
+
+    /// Returns the population to how it was when the snapshot was taken, provided it has not been evaluated or speciated since.
+    pub(crate) fn restore(&mut self, snapshot:PopulationSnapshot) {
+        self.organisms = snapshot.organisms;
+        self.to_mutate = snapshot.to_mutate;
+        self.generation = snapshot.generation;
+        self.gens_stagnated = snapshot.gens_stagnated;
+    }
 
+    /// Returns the persistent worker threads, starting them if this is the first call since the population was created or loaded.
+    pub fn get_evaluator(&mut self) -> &Evaluator {
+        let (num_threads, chunk_size) = (self.config.num_threads, self.config.eval_chunk_size);
+        self.evaluator.get_or_insert_with(|| Evaluator::new(num_threads, chunk_size))
     }
+
+    /// Evaluates genomes in worker processes from now on, or with the evaluator again if workers is None.
+    /// The pool is not saved in checkpoints, so must be set again after loading one.
+    pub fn set_workers(&mut self, workers:Option<WorkerPool>) { self.workers = workers; }
+    pub fn get_workers(&self) -> Option<&WorkerPool> { self.workers.as_ref() }
+
+    pub fn get_config(&self) -> &NeatConfig { &self.config }
     pub fn get_champion(&self) -> Option<Genome> { self.champion.clone() }
 
-    pub fn get_mean_fitness(&self) -> f64 { self.fitness_arr.lock().unwrap().iter().sum::<f64>() / POPULATION_SIZE as f64}
+    pub fn get_mean_fitness(&self) -> f64 { self.fitness_arr.iter().sum::<f64>() / self.config.population_size as f64}
     pub fn get_species_vec(&self) -> &Vec<Species> { &self.species_vec }
     //pub fn get_genome(&self, g_id:usize) -> &Genome { &self.organisms.read().unwrap()[g_id] }
     //pub fn get_genome_mut(&mut self, g_id:usize) -> &mut Genome { &mut self.organisms.write().unwrap()[g_id]}
-    pub fn get_fitness_vec(&self) -> [f64;POPULATION_SIZE] { self.fitness_arr.lock().unwrap().clone() }
+    pub fn get_fitness_vec(&self) -> Vec<f64> { self.fitness_arr.clone() }
     pub fn get_max_fitness(&self) -> f64 { self.max_fitness }
+    pub fn get_generation(&self) -> usize { self.generation }
+    pub fn get_gens_stagnated(&self) -> usize { self.gens_stagnated }
+    pub fn get_evaluations(&self) -> usize { self.evaluations }
+    pub fn has_new_champion(&self) -> bool { self.new_champion }
+    pub fn get_failures(&self) -> &Vec<EvalFailure> { &self.failures }
+    pub fn get_created_species(&self) -> &Vec<usize> { &self.created_species }
+    pub fn get_extinct_species(&self) -> &Vec<usize> { &self.extinct_species }
+    pub fn get_culled_species(&self) -> &Vec<usize> { &self.culled_species }
+    pub fn get_compatability_threshold(&self) -> f64 { self.compatability_threshold }
     pub fn species_len(&self) -> usize { self.species_vec.len() }
     pub fn should_mutate(&self, g_id:usize) -> bool {self.to_mutate[g_id]}
 
@@ -243,47 +362,42 @@ impl Population {
         }
     }
 
-    pub fn new_xor() -> Self {
-        Self {
-
-            organisms: 
-            {
-                let mut v = Arc::new(RwLock::new(Vec::new()));
-                let mut v_writer = v.write().unwrap();
-                for _i in 0..POPULATION_SIZE {
-                    v_writer.push(Genome::new_init_xor());
-                }
-                drop(v_writer);
-                v
-            },
-            to_mutate: [true;POPULATION_SIZE],
-            fitness_arr: Arc::new(Mutex::new([0.0;POPULATION_SIZE])),
-            max_fitness: 0.0,
-            champion: None,
-
-            species_vec: Vec::new(),
-            compatability_threshold: COMPATABILITY_THRESHOLD,
-            pop_avg_adj_fit: 0.0,
-            species_id_counter: 0,
-            generation: 0,
-            gens_stagnated: 0,
-        }
+    pub fn new_xor(config:&NeatConfig) -> Self {
+        let organisms = (0..config.population_size).map(|_| Genome::new_init_xor()).collect();
+        Self::with_organisms(organisms, config)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::population::Population;
+    use std::sync::Arc;
+    use rand::SeedableRng;
+    use crate::population::{Population, FitnessFn};
+    use crate::util::NeatRng;
     use crate::genetics::{NodeGene}; 
-    use crate::config::{POPULATION_SIZE};
+    use crate::config::NeatConfig;
     use crate::test_environments::xor;
 
     #[test]
     fn xor_zero_fitness_test() {
         let node_gene_pool= NodeGene::xor_node_gene_pool();
-        let mut population = Population::new(&node_gene_pool);
-        population.calculate_fitnesses(xor);
+        let config = NeatConfig::new(2, 1);
+        let mut population = Population::new(&node_gene_pool, &config);
+        let env: FitnessFn = Arc::new(xor);
+        population.calculate_fitnesses(&env, &mut NeatRng::seed_from_u64(0));
         let sum:f64 = population.get_fitness_vec().iter().sum();
-        assert!(sum == (POPULATION_SIZE * 4) as f64);
+        assert!(sum == (config.population_size * 4) as f64);
+    }
+
+    #[test]
+    fn closure_captures_context() {
+        let node_gene_pool= NodeGene::xor_node_gene_pool();
+        let config = NeatConfig::new(2, 1);
+        let mut population = Population::new(&node_gene_pool, &config);
+        let dataset = Arc::new(vec![1.0, 2.0, 3.5]);
+        let env: FitnessFn = Arc::new(move |_g| dataset.iter().sum());
+        population.calculate_fitnesses(&env, &mut NeatRng::seed_from_u64(0));
+        assert!(population.get_fitness_vec().iter().all(|f| *f == 6.5));
+        assert!(population.get_max_fitness() == 6.5);
     }
 }
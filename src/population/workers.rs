@@ -0,0 +1,377 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use rand::Rng;
+
+use crate::genetics::Genome;
+use crate::util::seed_eval_rng;
+use super::FailureReason;
+use super::evaluator::panic_message;
+
+/// The environment variable through which a worker process is told where to connect.
+pub const WORKER_ADDR_VAR: &str = "NEAT_WORKER_ADDR";
+/// The environment variable holding the token a worker process must send first, proving it was started by the pool.
+pub const WORKER_TOKEN_VAR: &str = "NEAT_WORKER_TOKEN";
+const TOKEN_LEN: usize = 32; // hex digits of a random u128
+const MAX_FRAME_LEN: usize = 64 << 20; // larger frames are rejected before allocating, as the length comes from the peer
+
+/// Evaluates genomes in separate worker processes, for fitness functions which are not thread-safe or which leak memory.
+/// Each worker is a copy of command, which should call run_worker. Genomes are sent to workers over TCP on localhost,
+/// and a worker which crashes or times out is killed and replaced.
+pub struct WorkerPool {
+    command: Mutex<Command>,
+    workers: Vec<Mutex<Option<Worker>>>, // None after a worker has been killed, until it is restarted
+    startup_timeout: Duration, // how long a new worker has to connect
+    restarts: AtomicUsize,
+}
+
+/// A running worker process and its connection.
+struct Worker {
+    child: Child,
+    stream: TcpStream,
+}
+
+impl WorkerPool {
+
+    /// Starts num_workers copies of command, failing if any of them does not connect within 10 seconds.
+    pub fn new(command:Command, num_workers:usize) -> io::Result<Self> {
+        assert!(num_workers != 0);
+        let mut pool = Self {
+            command: Mutex::new(command),
+            workers: Vec::new(),
+            startup_timeout: Duration::from_secs(10),
+            restarts: AtomicUsize::new(0),
+        };
+        for _ in 0..num_workers {
+            let worker = pool.spawn_worker()?;
+            pool.workers.push(Mutex::new(Some(worker)));
+        }
+        Ok(pool)
+    }
+
+    /// Returns the fitness of each genome, in order, in the same way as Evaluator::evaluate.
+    /// A genome whose worker dies fails with FailureReason::Crashed. With a timeout, a worker which has not replied
+    /// to a genome within it is killed, however steadily it is sending.
+    pub fn evaluate(&self, organisms:&[Genome], seeds:&[u64], timeout:Option<Duration>) -> Vec<Result<f64, FailureReason>> {
+        assert_eq!(organisms.len(), seeds.len());
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<Option<Result<f64, FailureReason>>> = vec![None; organisms.len()];
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.workers.iter().map(|slot| {
+                let next = &next;
+                scope.spawn(move || {
+                    let mut slot = slot.lock().unwrap();
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= organisms.len() {
+                            break;
+                        }
+                        done.push((i, self.run_job(&mut slot, &organisms[i], seeds[i], timeout)));
+                    }
+                    done
+                })
+            }).collect();
+            for handle in handles {
+                for (i, result) in handle.join().unwrap() {
+                    results[i] = Some(result);
+                }
+            }
+        });
+        results.into_iter().map(|result| result.unwrap()).collect()
+    }
+
+    pub fn num_workers(&self) -> usize { self.workers.len() }
+    /// The number of workers replaced after crashing or timing out.
+    pub fn get_restarts(&self) -> usize { self.restarts.load(Ordering::Relaxed) }
+
+    /// Evaluates one genome. A worker which fails is killed and replaced straight away,
+    /// or before its next genome if it could not be restarted then.
+    fn run_job(&self, slot:&mut Option<Worker>, genome:&Genome, seed:u64, timeout:Option<Duration>) -> Result<f64, FailureReason> {
+        if slot.is_none() {
+            if let Err(err) = self.restart(slot) {
+                return Err(FailureReason::Crashed(format!("could not restart worker: {}", err)));
+            }
+        }
+        let worker = slot.as_mut().unwrap();
+        let err = match worker.request(genome, seed, timeout) {
+            Ok(result) => return result.map_err(FailureReason::Panicked),
+            Err(err) => err,
+        };
+        let status = slot.take().unwrap().stop();
+        let _ = self.restart(slot);
+        match (err.kind(), timeout) {
+            (io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut, Some(limit)) => Err(FailureReason::TimedOut(limit)),
+            _ => Err(FailureReason::Crashed(match status {
+                Some(status) if !status.success() => format!("worker exited with {}", status),
+                _ => err.to_string(),
+            })),
+        }
+    }
+
+    fn restart(&self, slot:&mut Option<Worker>) -> io::Result<()> {
+        *slot = Some(self.spawn_worker()?);
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Starts a worker on its own listener, so concurrent restarts cannot pick up each other's connections.
+    /// Any local process can connect to the listener, so only a connection which first sends the worker's token is used.
+    /// The child is killed on every failure after it is spawned, as dropping a Child leaves the process running.
+    fn spawn_worker(&self) -> io::Result<Worker> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        listener.set_nonblocking(true)?;
+        let token = format!("{:0width$x}", rand::thread_rng().gen::<u128>(), width = TOKEN_LEN);
+        let mut child = self.command.lock().unwrap()
+            .env(WORKER_ADDR_VAR, listener.local_addr()?.to_string())
+            .env(WORKER_TOKEN_VAR, &token)
+            .spawn()?;
+
+        let deadline = Instant::now() + self.startup_timeout;
+        let failure = loop {
+            match listener.accept() {
+                Ok((stream, _)) => match handshake(stream, &token, deadline) {
+                    Ok(Some(stream)) => return Ok(Worker { child, stream }),
+                    Ok(None) => {}
+                    Err(err) => break err,
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => break err,
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => break io::Error::other(format!("worker exited with {} before connecting", status)),
+                Ok(None) => {}
+                Err(err) => break err,
+            }
+            if Instant::now() > deadline {
+                break io::Error::new(io::ErrorKind::TimedOut, format!("worker did not connect within {:?}", self.startup_timeout));
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        Err(failure)
+    }
+}
+
+/// Returns the stream if the peer sends the token before the deadline, or None if it is some other connection.
+fn handshake(mut stream:TcpStream, token:&str, deadline:Instant) -> io::Result<Option<TcpStream>> {
+    stream.set_nonblocking(false)?;
+    let remaining = deadline.saturating_duration_since(Instant::now()).max(Duration::from_millis(1));
+    stream.set_read_timeout(Some(remaining))?;
+    let mut received = [0; TOKEN_LEN];
+    if stream.read_exact(&mut received).is_err() || received != token.as_bytes() {
+        return Ok(None)
+    }
+    stream.set_nodelay(true)?;
+    Ok(Some(stream))
+}
+
+impl Worker {
+
+    /// Sends a genome and waits for its fitness, or the message it panicked with.
+    fn request(&mut self, genome:&Genome, seed:u64, timeout:Option<Duration>) -> io::Result<Result<f64, String>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        self.stream.set_read_timeout(timeout)?;
+        write_frame(&mut self.stream, &bincode::serialize(&(seed, genome)).map_err(invalid_data)?)?;
+        bincode::deserialize(&read_frame(&mut self.stream, deadline)?).map_err(invalid_data)
+    }
+
+    /// Kills the worker, returning how it exited.
+    fn stop(mut self) -> Option<ExitStatus> {
+        let _ = self.child.kill();
+        self.child.wait().ok()
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Whether this process was started by a WorkerPool, and so should call run_worker.
+pub fn is_worker() -> bool {
+    env::var_os(WORKER_ADDR_VAR).is_some()
+}
+
+/// Connects to the WorkerPool which started this process and evaluates genomes with env until the pool disconnects.
+/// Each genome's eval rng is seeded the same as it would be by an Evaluator, and panics are sent back as failures.
+pub fn run_worker<F>(env:F) -> io::Result<()> where F: Fn(&Genome) -> f64 {
+    let addr = env::var(WORKER_ADDR_VAR)
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("{} is not set, so this is not a worker process", WORKER_ADDR_VAR)))?;
+    let token = env::var(WORKER_TOKEN_VAR)
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("{} is not set", WORKER_TOKEN_VAR)))?;
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    stream.write_all(token.as_bytes())?;
+    loop {
+        let frame = match read_frame(&mut stream, None) {
+            Ok(frame) => frame,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let (seed, genome): (u64, Genome) = bincode::deserialize(&frame).map_err(invalid_data)?;
+        seed_eval_rng(seed);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| env(&genome))).map_err(panic_message);
+        write_frame(&mut stream, &bincode::serialize(&result).map_err(invalid_data)?)?;
+    }
+}
+
+/// Messages are bincode, prefixed with their length as a little-endian u32.
+fn write_frame(stream:&mut TcpStream, bytes:&[u8]) -> io::Result<()> {
+    if bytes.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is over the {} byte limit", bytes.len(), MAX_FRAME_LEN)))
+    }
+    let len = bytes.len() as u32;
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(bytes)
+}
+
+/// Reads a whole frame before the deadline, if there is one.
+fn read_frame(stream:&mut TcpStream, deadline:Option<Instant>) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    read_exact_by(stream, &mut len, deadline)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is over the {} byte limit", len, MAX_FRAME_LEN)))
+    }
+    let mut bytes = vec![0; len];
+    read_exact_by(stream, &mut bytes, deadline)?;
+    Ok(bytes)
+}
+
+/// Like read_exact, but shortens the read timeout as the deadline approaches, so a peer sending a byte at a time cannot outlast it.
+fn read_exact_by(stream:&mut TcpStream, buf:&mut [u8], deadline:Option<Instant>) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline passed before the frame was read"))
+            }
+            stream.set_read_timeout(Some(remaining))?;
+        }
+        match stream.read(&mut buf[filled..]) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err:E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process::{Command, Stdio};
+    use std::sync::Arc;
+    use crate::{Evaluator, FitnessFn, ActivationFn};
+    use crate::genetics::{Genome, NodeGene, NodeType};
+    use crate::test_environments::xor;
+    use std::io::{self, Write};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use super::{WorkerPool, FailureReason, run_worker, is_worker, read_frame, WORKER_ADDR_VAR, TOKEN_LEN};
+
+    /// Only does anything when run by a WorkerPool: aborts on genomes with one hidden node, and panics on those with two.
+    #[test]
+    #[ignore]
+    fn worker_process() {
+        if !is_worker() {
+            return;
+        }
+        run_worker(|genome| match genome.get_nodes().len() {
+            7 => std::process::abort(),
+            8 => panic!("bad genome"),
+            _ => xor(genome),
+        }).unwrap();
+    }
+
+    /// Only does anything when run by a WorkerPool: connects with the wrong token, then exits.
+    #[test]
+    #[ignore]
+    fn impostor_process() {
+        if !is_worker() {
+            return;
+        }
+        let mut stream = TcpStream::connect(env::var(WORKER_ADDR_VAR).unwrap()).unwrap();
+        stream.write_all(&[b'0'; TOKEN_LEN]).unwrap();
+    }
+
+    fn test_command(test:&str) -> Command {
+        let mut command = Command::new(env::current_exe().unwrap());
+        command.args(["--ignored", "--exact", test, "--test-threads=1"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+
+    #[test]
+    fn rejects_connections_without_the_token() {
+        let err = WorkerPool::new(test_command("population::workers::tests::impostor_process"), 1).err().unwrap();
+        assert!(err.to_string().contains("before connecting"), "{}", err);
+    }
+
+    #[test]
+    fn evaluates_in_worker_processes() {
+        let pool = WorkerPool::new(test_command("population::workers::tests::worker_process"), 2).unwrap();
+
+        let mut organisms: Vec<Genome> = (0..6).map(|_| Genome::new_init_xor()).collect();
+        organisms[1].get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 6, 0.5, 0.3, ActivationFn::Sigmoid));
+        organisms[4].get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 6, 0.5, 0.3, ActivationFn::Sigmoid));
+        organisms[4].get_nodes_mut().push(NodeGene::new(NodeType::Hidden, 7, 0.5, 0.3, ActivationFn::Sigmoid));
+        let seeds: Vec<u64> = (0..6).collect();
+
+        let env: FitnessFn = Arc::new(xor);
+        let expected = Evaluator::new(1, 1).evaluate(&organisms, &env, &seeds, None);
+        let results = pool.evaluate(&organisms, &seeds, None);
+        assert!(matches!(results[1], Err(FailureReason::Crashed(_))));
+        assert_eq!(results[4], Err(FailureReason::Panicked(String::from("bad genome"))));
+        for i in [0, 2, 3, 5] {
+            assert_eq!(results[i], expected[i]);
+        }
+
+        // The crashed worker is replaced
+        assert_eq!(pool.evaluate(&organisms[2..4], &seeds[2..4], None), expected[2..4].to_vec());
+        assert_eq!(pool.get_restarts(), 1);
+    }
+
+    #[test]
+    fn frames_are_bounded() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut receiver = listener.accept().unwrap().0;
+
+        sender.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        assert_eq!(read_frame(&mut receiver, None).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // A peer trickling a frame still misses the deadline
+        let trickle = thread::spawn(move || {
+            sender.write_all(&100u32.to_le_bytes()).unwrap();
+            for _ in 0..100 {
+                if sender.write_all(&[0]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let err = read_frame(&mut receiver, Some(Instant::now() + Duration::from_millis(100))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        drop(receiver);
+        trickle.join().unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::population::{Population, EvalFailure};
+
+/// Time spent in each phase of a generation.
+/// reproduce & mutate are zero for the first generation, as it has no parents.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseTimings {
+    pub reproduce: Duration,
+    pub mutate: Duration,
+    pub evaluate: Duration,
+    pub speciate: Duration,
+}
+
+impl PhaseTimings {
+    pub fn total(&self) -> Duration {
+        self.reproduce + self.mutate + self.evaluate + self.speciate
+    }
+}
+
+/// A snapshot of a single species after speciation.
+#[derive(Debug, Clone)]
+pub struct SpeciesReport {
+    pub id: usize,
+    pub size: usize,
+    pub mean_fitness: f64,
+    pub best_fitness: f64,
+    pub gens_stagnated: usize,
+}
+
+/// Summary of a generation, returned by Neat::step().
+#[derive(Debug, Clone)]
+pub struct GenerationReport {
+    pub generation: usize,
+    pub max_fitness: f64, // best fitness of this generation
+    pub mean_fitness: f64,
+    pub champion_fitness: f64, // best fitness of any generation so far
+    pub gens_stagnated: usize, // generations since champion_fitness last improved
+    pub evaluations: usize, // genomes evaluated by every generation so far
+    pub compatability_threshold: f64,
+    pub species: Vec<SpeciesReport>,
+    pub timings: PhaseTimings,
+
+    pub new_champion: bool, // whether champion_fitness improved this generation
+    pub created_species: Vec<usize>, // ids of species founded this generation
+    pub extinct_species: Vec<usize>, // ids of species left without members this generation
+    pub culled_species: Vec<usize>, // ids of species stagnated beyond dropoff_age, whose fitnesses were penalised
+    pub failed_genomes: Vec<EvalFailure>, // genomes whose fitness function panicked or timed out, given config.failed_fitness
+}
+
+impl GenerationReport {
+
+    /// Builds a report from a population which has just been evaluated and speciated.
+    pub fn new(population:&Population, timings:PhaseTimings) -> Self {
+        let fitnesses = population.get_fitness_vec();
+        Self {
+            generation: population.get_generation(),
+            max_fitness: fitnesses.iter().cloned().fold(f64::MIN, f64::max),
+            mean_fitness: population.get_mean_fitness(),
+            champion_fitness: population.get_max_fitness(),
+            gens_stagnated: population.get_gens_stagnated(),
+            evaluations: population.get_evaluations(),
+            compatability_threshold: population.get_compatability_threshold(),
+            species: population.get_species_vec().iter().map(|species| SpeciesReport {
+                id: species.get_id(),
+                size: species.len(),
+                mean_fitness: species.get_mean_fitness(),
+                best_fitness: species.get_best_fitness(),
+                gens_stagnated: species.get_gens_stagnated(),
+            }).collect(),
+            timings,
+
+            new_champion: population.has_new_champion(),
+            created_species: population.get_created_species().clone(),
+            extinct_species: population.get_extinct_species().clone(),
+            culled_species: population.get_culled_species().clone(),
+            failed_genomes: population.get_failures().clone(),
+        }
+    }
+}
+
+impl fmt::Display for GenerationReport {
+    /// Formats the report as a table of the population and each of its species.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let population_size: usize = self.species.iter().map(|species| species.size).sum();
+        writeln!(f, "===== Generation {} ======", self.generation)?;
+        writeln!(f, "Compatability threshold: {}", self.compatability_threshold)?;
+        writeln!(f, "  ID    Num Members      Mean Fitness       Best Fitness      Gens Stagnant")?;
+        writeln!(f, "Pop,          {},          {:.2},         {:.2}              {}", population_size, self.mean_fitness, self.champion_fitness, self.gens_stagnated)?;
+        for species in self.species.iter() {
+            writeln!(f, "{}               {}           {:.2}             {:.2}           {}", species.id, species.size, species.mean_fitness, species.best_fitness, species.gens_stagnated)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::{Neat, NeatConfig, FitnessFn, FailureReason};
+    use crate::test_environments::xor;
+
+    #[test]
+    fn step_reports_each_generation() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        let mut neat = Neat::new_fully_connected(config);
+        let env: FitnessFn = Arc::new(xor);
+
+        let first = neat.step(&env);
+        assert_eq!(first.generation, 0);
+        assert_eq!(first.species.iter().map(|s| s.size).sum::<usize>(), 50);
+
+        let second = neat.step(&env);
+        assert_eq!(second.generation, 1);
+        assert!(second.champion_fitness >= first.champion_fitness);
+        assert!(second.max_fitness <= second.champion_fitness);
+    }
+
+    #[test]
+    fn step_reports_failed_genomes() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        let mut neat = Neat::new_fully_connected(config);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let env: FitnessFn = Arc::new(move |genome| {
+            if calls.fetch_add(1, Ordering::SeqCst).is_multiple_of(5) { panic!("unstable genome"); }
+            xor(genome)
+        });
+
+        let report = neat.step(&env);
+        assert_eq!(report.failed_genomes.len(), 10);
+        for failure in report.failed_genomes.iter() {
+            assert_eq!(failure.reason, FailureReason::Panicked(String::from("unstable genome")));
+            assert_eq!(neat.get_population().get_fitness_vec()[failure.genome_id], 0.0);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::genetics::Genome;
+use crate::report::GenerationReport;
+use crate::population::EvalFailure;
+use crate::checkpoint::CheckpointError;
+use super::Reporter;
+
+/// Writes every event to a log file, one line per event, followed by the generation table.
+/// Write errors are ignored so logging never interrupts training.
+pub struct FileReporter {
+    writer: BufWriter<File>,
+}
+
+impl FileReporter {
+    /// Creates the log file, truncating it if it already exists.
+    pub fn new<P: AsRef<Path>>(path:P) -> io::Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+}
+
+impl Reporter for FileReporter {
+    fn generation_end(&mut self, report:&GenerationReport) {
+        let _ = write!(self.writer, "{}", report);
+        let _ = self.writer.flush();
+    }
+
+    fn new_champion(&mut self, generation:usize, _champion:&Genome, fitness:f64) {
+        let _ = writeln!(self.writer, "generation {}: new champion with fitness {}", generation, fitness);
+    }
+
+    fn species_created(&mut self, generation:usize, species_id:usize) {
+        let _ = writeln!(self.writer, "generation {}: species {} created", generation, species_id);
+    }
+
+    fn species_extinct(&mut self, generation:usize, species_id:usize) {
+        let _ = writeln!(self.writer, "generation {}: species {} extinct", generation, species_id);
+    }
+
+    fn species_culled(&mut self, generation:usize, species_id:usize) {
+        let _ = writeln!(self.writer, "generation {}: species {} culled for stagnating", generation, species_id);
+    }
+
+    fn genome_failed(&mut self, generation:usize, failure:&EvalFailure) {
+        let _ = writeln!(self.writer, "generation {}: genome {} failed, {}", generation, failure.genome_id, failure.reason);
+    }
+
+    fn checkpoint_failed(&mut self, generation:usize, error:&CheckpointError) {
+        let _ = writeln!(self.writer, "generation {}: failed to save checkpoint, {}", generation, error);
+    }
+}
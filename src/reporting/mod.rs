@@ -0,0 +1,7 @@
+mod reporter;
+mod stdout_reporter;
+mod file_reporter;
+
+pub use reporter::{Reporter, SilentReporter};
+pub use stdout_reporter::StdoutReporter;
+pub use file_reporter::FileReporter;
@@ -0,0 +1,87 @@
+use crate::genetics::Genome;
+use crate::report::GenerationReport;
+use crate::population::EvalFailure;
+use crate::checkpoint::CheckpointError;
+
+/// Receives events from Neat as training progresses.
+/// Every hook does nothing by default, so implementors only override the events they care about.
+/// Reporters must be Send, so a Neat can be moved to another thread or awaited on a multi-threaded runtime.
+pub trait Reporter: Send {
+    /// Called before a generation is reproduced, mutated & evaluated.
+    fn generation_start(&mut self, _generation:usize) {}
+
+    /// Called once a generation has been evaluated and speciated.
+    fn generation_end(&mut self, _report:&GenerationReport) {}
+
+    /// Called when a genome beats the fitness of every genome before it.
+    fn new_champion(&mut self, _generation:usize, _champion:&Genome, _fitness:f64) {}
+
+    fn species_created(&mut self, _generation:usize, _species_id:usize) {}
+
+    fn species_extinct(&mut self, _generation:usize, _species_id:usize) {}
+
+    /// Called when a species has stagnated for longer than dropoff_age, so its fitnesses are penalised.
+    fn species_culled(&mut self, _generation:usize, _species_id:usize) {}
+
+    /// Called when a genome's fitness function panicked or timed out, so it was given config.failed_fitness.
+    fn genome_failed(&mut self, _generation:usize, _failure:&EvalFailure) {}
+
+    /// Called when the checkpoint for a generation could not be saved, so the previous checkpoint is the latest.
+    fn checkpoint_failed(&mut self, _generation:usize, _error:&CheckpointError) {}
+}
+
+/// A Reporter which ignores every event.
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use crate::{Neat, NeatConfig, FitnessFn, GenerationReport};
+    use crate::test_environments::xor;
+    use super::Reporter;
+
+    #[derive(Default)]
+    struct Counts {
+        starts: usize,
+        ends: usize,
+        created: usize,
+    }
+
+    struct CountingReporter(Arc<Mutex<Counts>>);
+
+    impl Reporter for CountingReporter {
+        fn generation_start(&mut self, _generation:usize) { self.0.lock().unwrap().starts += 1; }
+        fn generation_end(&mut self, _report:&GenerationReport) { self.0.lock().unwrap().ends += 1; }
+        fn species_created(&mut self, _generation:usize, _species_id:usize) { self.0.lock().unwrap().created += 1; }
+    }
+
+    #[test]
+    fn every_reporter_receives_events() {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        let mut neat = Neat::new_fully_connected(config);
+        let first = Arc::new(Mutex::new(Counts::default()));
+        let second = Arc::new(Mutex::new(Counts::default()));
+        neat.add_reporter(CountingReporter(Arc::clone(&first)));
+        neat.add_reporter(CountingReporter(Arc::clone(&second)));
+
+        let env: FitnessFn = Arc::new(xor);
+        let report = neat.step(&env);
+        neat.step(&env);
+
+        for counts in [first, second] {
+            let counts = counts.lock().unwrap();
+            assert_eq!(counts.starts, 2);
+            assert_eq!(counts.ends, 2);
+            assert!(counts.created >= report.created_species.len());
+        }
+    }
+
+    #[test]
+    fn neat_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Neat>();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::genetics::Genome;
+use crate::report::GenerationReport;
+use crate::population::EvalFailure;
+use crate::checkpoint::CheckpointError;
+use super::Reporter;
+
+/// Prints a table of the population and its species to the console after every generation.
+#[derive(Default)]
+pub struct StdoutReporter {
+    show_timings: bool,
+}
+
+impl StdoutReporter {
+    pub fn new() -> Self {
+        Self { show_timings: false }
+    }
+
+    /// Also print how long each phase of the generation took.
+    pub fn with_timings() -> Self {
+        Self { show_timings: true }
+    }
+}
+
+impl Reporter for StdoutReporter {
+    fn generation_end(&mut self, report:&GenerationReport) {
+        print!("{}", report);
+        if self.show_timings {
+            let t = &report.timings;
+            println!("reproduce: {:.2?}, mutate: {:.2?}, evaluate: {:.2?}, speciate: {:.2?}", t.reproduce, t.mutate, t.evaluate, t.speciate);
+        }
+    }
+
+    fn new_champion(&mut self, generation:usize, _champion:&Genome, fitness:f64) {
+        println!("New champion in generation {} with fitness {:.2}", generation, fitness);
+    }
+
+    fn genome_failed(&mut self, generation:usize, failure:&EvalFailure) {
+        println!("Genome {} failed in generation {}: {}", failure.genome_id, generation, failure.reason);
+    }
+
+    fn checkpoint_failed(&mut self, generation:usize, error:&CheckpointError) {
+        println!("Failed to save checkpoint for generation {}: {}", generation, error);
+    }
+}
@@ -1,9 +1,11 @@
 use rand::prelude::*;
+use serde::{Serialize, Deserialize};
 
-use crate::config::DROPOFF_AGE;
+use crate::config::NeatConfig;
 use crate::genetics::Genome;
 
 /// A collection of references to genomes which enables crossover of similar topologies and selection of parents based on their fitnesses.
+#[derive(Serialize, Deserialize)]
 pub struct Species {
     id: usize,
     representative: Genome,
@@ -43,7 +45,7 @@ impl Species {
     }
 
     /// Inserts a new genome into the species and updates relevent parameters
-    pub fn insert(&mut self, g_id:usize, mut fitness:f64) {
+    pub fn insert(&mut self, g_id:usize, mut fitness:f64, config:&NeatConfig) {
         if fitness > self.max_ever_fitness {
             self.max_ever_fitness = fitness;
             self.gens_stagnated = 0;
@@ -51,7 +53,7 @@ impl Species {
         if fitness > self.best_fitness {
             self.best_fitness = fitness;
         }
-        if self.gens_stagnated > DROPOFF_AGE {
+        if self.gens_stagnated > config.dropoff_age {
             fitness *= 0.001;
         }
         self.members.push((g_id, fitness, -1.0));
@@ -85,8 +87,8 @@ impl Species {
     }
     
     /// Returns a random genome reference with probability directly proportional to each member's fitness
-    pub fn get_fit_member_id(&self) -> usize {
-        let mut rem_adj_fit = rand::thread_rng().gen_range(0.0..self.total_adj_fitness);
+    pub fn get_fit_member_id<R: Rng + ?Sized>(&self, rng:&mut R) -> usize {
+        let mut rem_adj_fit = rng.gen_range(0.0..self.total_adj_fitness);
         let mut index = 0;
         while rem_adj_fit > 0.0 {
             rem_adj_fit -= self.members[index].2;
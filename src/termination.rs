@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use crate::genetics::Genome;
+use crate::report::GenerationReport;
+
+/// Why training stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    TargetFitness,
+    MaxGenerations,
+    TimeBudget,
+    EvaluationBudget,
+    Stagnation,
+}
+
+/// The conditions under which training stops. Training stops as soon as any condition which is set is met.
+/// Conditions which are None are ignored, so Termination::default() never stops.
+/// Budgets count from the start of the train_until() call, so a run resumed from a checkpoint gets a fresh budget.
+#[derive(Debug, Clone, Default)]
+pub struct Termination {
+    pub target_fitness: Option<f64>, // stop when the champion's fitness exceeds this value
+    pub max_generations: Option<usize>, // stop after this many generations have been evaluated
+    pub max_duration: Option<Duration>, // wall-clock budget
+    pub max_evaluations: Option<usize>, // stop once this many genomes have been evaluated
+    pub max_stagnation: Option<usize>, // stop when the champion has not improved for this many generations
+}
+
+impl Termination {
+
+    /// Stops only once the champion's fitness exceeds target_fitness.
+    pub fn target_fitness(target_fitness:f64) -> Self {
+        Self {
+            target_fitness: Some(target_fitness),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the reason to stop training after the reported generation, if any.
+    /// progress is what training has used since it started, against which the budgets are checked.
+    /// A reached target fitness takes priority over any exhausted budget.
+    pub fn check(&self, report:&GenerationReport, progress:&Progress) -> Option<StopReason> {
+        if let Some(target_fitness) = self.target_fitness {
+            if report.champion_fitness > target_fitness {
+                return Some(StopReason::TargetFitness)
+            }
+        }
+        if let Some(max_generations) = self.max_generations {
+            if progress.generations >= max_generations {
+                return Some(StopReason::MaxGenerations)
+            }
+        }
+        if let Some(max_duration) = self.max_duration {
+            if progress.elapsed >= max_duration {
+                return Some(StopReason::TimeBudget)
+            }
+        }
+        if let Some(max_evaluations) = self.max_evaluations {
+            if progress.evaluations >= max_evaluations {
+                return Some(StopReason::EvaluationBudget)
+            }
+        }
+        if let Some(max_stagnation) = self.max_stagnation {
+            if report.gens_stagnated >= max_stagnation {
+                return Some(StopReason::Stagnation)
+            }
+        }
+        None
+    }
+}
+
+/// How much training has done since it started.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    pub generations: usize,
+    pub evaluations: usize,
+    pub elapsed: Duration,
+}
+
+/// The outcome of Neat::train_until().
+#[derive(Clone)]
+pub struct TrainingResult {
+    pub champion: Genome,
+    pub reason: StopReason,
+    pub report: GenerationReport, // report of the final generation
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Neat, NeatConfig};
+    use crate::test_environments::xor;
+    use super::{Termination, StopReason};
+
+    fn small_xor_neat() -> Neat {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 50;
+        Neat::new_fully_connected(config)
+    }
+
+    #[test]
+    fn stops_at_max_generations() {
+        let mut neat = small_xor_neat();
+        let termination = Termination {
+            max_generations: Some(3),
+            ..Termination::target_fitness(f64::MAX)
+        };
+        let result = neat.train_until(xor, &termination);
+        assert_eq!(result.reason, StopReason::MaxGenerations);
+        assert_eq!(result.report.generation, 2);
+    }
+
+    #[test]
+    fn stops_at_evaluation_budget() {
+        let mut neat = small_xor_neat();
+        let termination = Termination {
+            max_evaluations: Some(120),
+            ..Termination::default()
+        };
+        let result = neat.train_until(xor, &termination);
+        assert_eq!(result.reason, StopReason::EvaluationBudget);
+        assert_eq!(result.report.evaluations, 150);
+    }
+
+    #[test]
+    fn budgets_count_from_the_start_of_training() {
+        let mut neat = small_xor_neat();
+        let termination = Termination {
+            max_generations: Some(3),
+            ..Termination::target_fitness(f64::MAX)
+        };
+        neat.train_until(xor, &termination);
+        let result = neat.train_until(xor, &termination);
+        assert_eq!(result.reason, StopReason::MaxGenerations);
+        assert_eq!(result.report.generation, 5);
+
+        let termination = Termination {
+            max_evaluations: Some(120),
+            ..Termination::default()
+        };
+        let result = neat.train_until(xor, &termination);
+        assert_eq!(result.reason, StopReason::EvaluationBudget);
+        assert_eq!(result.report.evaluations, 450);
+    }
+
+    #[test]
+    fn target_fitness_takes_priority() {
+        let mut neat = small_xor_neat();
+        let termination = Termination {
+            max_generations: Some(1),
+            ..Termination::target_fitness(-1.0)
+        };
+        let result = neat.train_until(xor, &termination);
+        assert_eq!(result.reason, StopReason::TargetFitness);
+    }
+}
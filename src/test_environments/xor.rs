@@ -1,18 +1,18 @@
-use crate::config::{INPUTS, BIAS, OUTPUTS};
 use crate::genetics::Genome;
 use crate::neural_network::FeedForwardNetwork;
 
 pub fn xor(organism: &Genome) -> f64 {
-    if INPUTS != 2 || OUTPUTS != 1 || BIAS != true {
-        panic!("invalid inputs, bias and outputs for xor");
-    }
-    let mut output:[f64;OUTPUTS];
+    let mut output:Vec<f64>;
     let mut distance:f64;
     // let mut neural_network = NeuralNetwork::new(organism);
     let mut neural_network = FeedForwardNetwork::new(organism);
+    // A bias node is optional, as config.node_parameters gives each node its own bias
+    if neural_network.num_inputs() != 2 || neural_network.num_outputs() != 1 {
+        panic!("invalid inputs and outputs for xor");
+    }
 
     // organism.print();
-    let mut input = vec![0.0;INPUTS];
+    let mut input:Vec<f64>;
 
     input = vec![0.0, 0.0];
     output = neural_network.activate(input.clone());
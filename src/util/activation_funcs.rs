@@ -1,6 +1,91 @@
-use crate::OUTPUTS;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
 
-pub fn softmax(input: [f64;OUTPUTS]) -> [usize;OUTPUTS] {
+/// The function a node applies to its summed, weighted inputs.
+// Genome binaries store the variant index, so new functions must be appended to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivationFn {
+    Sigmoid,
+    Tanh,
+    Relu,
+    Identity,
+    Gaussian,
+    Sin,
+    Abs,
+    Step,
+    Softplus,
+    Clamped,
+}
+
+impl ActivationFn {
+
+    /// Every activation function, in declaration order.
+    pub const ALL:[ActivationFn; 10] = [
+        ActivationFn::Sigmoid,
+        ActivationFn::Tanh,
+        ActivationFn::Relu,
+        ActivationFn::Identity,
+        ActivationFn::Gaussian,
+        ActivationFn::Sin,
+        ActivationFn::Abs,
+        ActivationFn::Step,
+        ActivationFn::Softplus,
+        ActivationFn::Clamped,
+    ];
+
+    pub fn apply(&self, x:f64) -> f64 {
+        match self {
+            ActivationFn::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            ActivationFn::Tanh => x.tanh(),
+            ActivationFn::Relu => x.max(0.0),
+            ActivationFn::Identity => x,
+            ActivationFn::Gaussian => (-x * x).exp(),
+            ActivationFn::Sin => x.sin(),
+            ActivationFn::Abs => x.abs(),
+            ActivationFn::Step => if x > 0.0 { 1.0 } else { 0.0 },
+            ActivationFn::Softplus => x.max(0.0) + (-x.abs()).exp().ln_1p(), // ln(1 + e^x) without overflowing e^x
+            ActivationFn::Clamped => x.clamp(-1.0, 1.0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivationFn::Sigmoid => "sigmoid",
+            ActivationFn::Tanh => "tanh",
+            ActivationFn::Relu => "relu",
+            ActivationFn::Identity => "identity",
+            ActivationFn::Gaussian => "gaussian",
+            ActivationFn::Sin => "sin",
+            ActivationFn::Abs => "abs",
+            ActivationFn::Step => "step",
+            ActivationFn::Softplus => "softplus",
+            ActivationFn::Clamped => "clamped",
+        }
+    }
+}
+
+impl fmt::Display for ActivationFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ActivationFn {
+    type Err = String;
+
+    /// Parses the lowercase name of an activation function, e.g. "tanh".
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        ActivationFn::ALL.iter()
+            .find(|activation| activation.name() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("unknown activation function '{}'", s))
+    }
+}
+
+/// Returns a one-hot vector with a 1 at the index of the largest input.
+pub fn argmax(input: &[f64]) -> Vec<usize> {
     let mut idx = 0;
     let mut max = input[0];
     for i in 1..input.len() {
@@ -9,7 +94,46 @@ pub fn softmax(input: [f64;OUTPUTS]) -> [usize;OUTPUTS] {
             max = input[i];
         }
     }
-    let mut output = [0;OUTPUTS];
+    let mut output = vec![0;input.len()];
     output[idx] = 1;
     output
-}
\ No newline at end of file
+}
+
+/// Returns the softmax of the input, a probability distribution weighted towards the largest inputs.
+pub fn softmax(input: &[f64]) -> Vec<f64> {
+    let max = input.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = input.iter().map(|x| (x - max).exp()).collect();
+    let sum: f64 = exps.iter().sum();
+    exps.iter().map(|x| x / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActivationFn, argmax, softmax};
+
+    #[test]
+    fn activation_names_roundtrip() {
+        for activation in ActivationFn::ALL {
+            assert_eq!(activation.name().parse::<ActivationFn>(), Ok(activation));
+        }
+        assert!("swish".parse::<ActivationFn>().is_err());
+        assert_eq!(ActivationFn::Relu.apply(-2.0), 0.0);
+        assert_eq!(ActivationFn::Step.apply(0.5), 1.0);
+    }
+
+    #[test]
+    fn softplus_is_stable() {
+        assert_eq!(ActivationFn::Softplus.apply(1000.0), 1000.0);
+        assert_eq!(ActivationFn::Softplus.apply(-1000.0), 0.0);
+        assert!((ActivationFn::Softplus.apply(0.0) - 2f64.ln()).abs() < 1e-12);
+        assert!((ActivationFn::Softplus.apply(1.0) - 1f64.exp().ln_1p()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn argmax_and_softmax() {
+        assert_eq!(argmax(&[0.1, 0.7, 0.2]), vec![0, 1, 0]);
+        let probabilities = softmax(&[1.0, 2.0, 3.0]);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(probabilities[2] > probabilities[1] && probabilities[1] > probabilities[0]);
+    }
+}
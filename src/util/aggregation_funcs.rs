@@ -0,0 +1,103 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+/// The function a node uses to combine its weighted inputs into a single value.
+// Genome binaries store the variant index, so new functions must be appended to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregationFn {
+    Sum,
+    Product,
+    Max,
+    Min,
+    Mean,
+    Median,
+    MaxAbs,
+}
+
+impl AggregationFn {
+
+    /// Every aggregation function, in declaration order.
+    pub const ALL:[AggregationFn; 7] = [
+        AggregationFn::Sum,
+        AggregationFn::Product,
+        AggregationFn::Max,
+        AggregationFn::Min,
+        AggregationFn::Mean,
+        AggregationFn::Median,
+        AggregationFn::MaxAbs,
+    ];
+
+    /// Combines the weighted inputs of a node. A node without inputs aggregates to 0.
+    pub fn apply(&self, values:&[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0
+        }
+        match self {
+            AggregationFn::Sum => values.iter().fold(0.0, |acc, x| acc + x),
+            AggregationFn::Product => values.iter().fold(1.0, |acc, x| acc * x),
+            AggregationFn::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            AggregationFn::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            AggregationFn::Mean => values.iter().fold(0.0, |acc, x| acc + x) / values.len() as f64,
+            AggregationFn::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] }
+            }
+            AggregationFn::MaxAbs => values.iter().copied().fold(0.0, |acc:f64, x| if x.abs() > acc.abs() { x } else { acc }),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregationFn::Sum => "sum",
+            AggregationFn::Product => "product",
+            AggregationFn::Max => "max",
+            AggregationFn::Min => "min",
+            AggregationFn::Mean => "mean",
+            AggregationFn::Median => "median",
+            AggregationFn::MaxAbs => "maxabs",
+        }
+    }
+}
+
+impl fmt::Display for AggregationFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for AggregationFn {
+    type Err = String;
+
+    /// Parses the lowercase name of an aggregation function, e.g. "max".
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        AggregationFn::ALL.iter()
+            .find(|aggregation| aggregation.name() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("unknown aggregation function '{}'", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AggregationFn;
+
+    #[test]
+    fn aggregates() {
+        let values = [2.0, -5.0, 3.0, 1.0];
+        assert_eq!(AggregationFn::Sum.apply(&values), 1.0);
+        assert_eq!(AggregationFn::Product.apply(&values), -30.0);
+        assert_eq!(AggregationFn::Max.apply(&values), 3.0);
+        assert_eq!(AggregationFn::Min.apply(&values), -5.0);
+        assert_eq!(AggregationFn::Mean.apply(&values), 0.25);
+        assert_eq!(AggregationFn::Median.apply(&values), 1.5);
+        assert_eq!(AggregationFn::MaxAbs.apply(&values), -5.0);
+        for aggregation in AggregationFn::ALL {
+            assert_eq!(aggregation.apply(&[]), 0.0);
+            assert_eq!(aggregation.name().parse::<AggregationFn>(), Ok(aggregation));
+        }
+    }
+}
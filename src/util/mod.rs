@@ -1,7 +1,10 @@
 mod vecset;
 mod activation_funcs;
-mod thread_pool;
+mod aggregation_funcs;
+mod rng;
 
-pub use activation_funcs::softmax;
+pub use activation_funcs::{ActivationFn, argmax, softmax};
+pub use aggregation_funcs::AggregationFn;
 pub use vecset::VecSet;
-pub use thread_pool::ThreadPool;
\ No newline at end of file
+pub use rng::{NeatRng, with_eval_rng};
+pub(crate) use rng::{seed_eval_rng, swap_eval_rng};
\ No newline at end of file
@@ -0,0 +1,90 @@
+use std::cell::RefCell;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The random number generator behind every random decision made during evolution.
+pub type NeatRng = ChaCha8Rng;
+
+thread_local! {
+    static EVAL_RNG: RefCell<NeatRng> = RefCell::new(NeatRng::seed_from_u64(0));
+}
+
+/// Gives a fitness function access to a random number generator seeded for the genome being evaluated.
+/// Each genome's stream is derived from the Neat seed, so evaluation is reproducible no matter which thread runs it.
+pub fn with_eval_rng<T, F: FnOnce(&mut NeatRng) -> T>(f:F) -> T {
+    EVAL_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Reseeds the current thread's evaluation rng before evaluating a genome.
+pub(crate) fn seed_eval_rng(seed:u64) {
+    EVAL_RNG.with(|rng| *rng.borrow_mut() = NeatRng::seed_from_u64(seed));
+}
+
+/// Swaps rng with the current thread's evaluation rng, so an async evaluation can carry its own stream between polls.
+pub(crate) fn swap_eval_rng(rng:&mut NeatRng) {
+    EVAL_RNG.with(|eval_rng| std::mem::swap(&mut *eval_rng.borrow_mut(), rng));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rand::Rng;
+    use crate::{Neat, NeatConfig, FitnessFn, Genome, Termination};
+    use crate::test_environments::xor;
+    use super::with_eval_rng;
+
+    fn seeded_xor_neat(seed:u64) -> Neat {
+        seeded_xor_neat_on(seed, NeatConfig::default().num_threads)
+    }
+
+    fn seeded_xor_neat_on(seed:u64, num_threads:usize) -> Neat {
+        let mut config = NeatConfig::new(2, 1);
+        config.population_size = 60;
+        config.num_threads = num_threads;
+        config.eval_chunk_size = 1;
+        config.m_node = 0.2;
+        config.m_conn = 0.2;
+        config.seed = Some(seed);
+        Neat::new_fully_connected(config)
+    }
+
+    fn champion_bytes(champion:&Genome) -> Vec<u8> {
+        bincode::serialize(champion).unwrap()
+    }
+
+    #[test]
+    fn same_seed_same_champion() {
+        let termination = Termination { max_generations: Some(15), ..Termination::default() };
+        let a = seeded_xor_neat(7).train_until(xor, &termination);
+        let b = seeded_xor_neat(7).train_until(xor, &termination);
+        assert_eq!(champion_bytes(&a.champion), champion_bytes(&b.champion));
+        assert_eq!(a.report.mean_fitness, b.report.mean_fitness);
+    }
+
+    #[test]
+    fn eval_rng_is_seeded_per_genome() {
+        let env: FitnessFn = Arc::new(|_g| with_eval_rng(|rng| rng.gen_range(0.0..1.0)));
+        let mut a = seeded_xor_neat(3);
+        let mut b = seeded_xor_neat(3);
+        a.step(&env);
+        b.step(&env);
+        let fitnesses = a.get_population().get_fitness_vec();
+        assert_eq!(fitnesses, b.get_population().get_fitness_vec());
+        assert!(fitnesses.iter().any(|f| *f != fitnesses[0]));
+    }
+
+    #[test]
+    fn same_seed_same_population_on_any_threads() {
+        let termination = Termination { max_generations: Some(15), ..Termination::default() };
+        let mut a = seeded_xor_neat_on(11, 1);
+        let mut b = seeded_xor_neat_on(11, 4);
+        a.train_until(xor, &termination);
+        b.train_until(xor, &termination);
+        let (a_organisms, b_organisms) = (a.get_population().organisms.read().unwrap(), b.get_population().organisms.read().unwrap());
+        for (a_genome, b_genome) in a_organisms.iter().zip(b_organisms.iter()) {
+            assert_eq!(champion_bytes(a_genome), champion_bytes(b_genome));
+        }
+        assert_eq!(a.get_connection_pool().len(), b.get_connection_pool().len());
+        assert_eq!(a.get_node_pool().len(), b.get_node_pool().len());
+    }
+}
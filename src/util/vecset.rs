@@ -1,10 +1,13 @@
 use std::collections::HashSet;
 use rand::prelude::*;
+use serde::{Serialize, Deserialize};
 
 use crate::genetics::Gene;
 
 //Isn't this essentially a HashMap??
-#[derive(Clone)]
+// Serialized as just the vec, the hashset is rebuilt on deserialization, which fails on duplicate innovation numbers.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "Vec<T>", try_from = "Vec<T>", bound(serialize = "T: Gene + Clone + Serialize", deserialize = "T: Gene + Clone + Deserialize<'de>"))]
 pub struct VecSet<T> {
     vec: Vec<T>, // Store NodeGenes & ConnectionGenes
     hashset: HashSet<usize>, // Store Innovation Numbers, to prevent duplicates in vec
@@ -27,7 +30,8 @@ impl<T> VecSet<T> where T: Gene + Clone {
         return false
     }
 
-    pub fn insert_sorted(&mut self, gene:T) {
+    /// Inserts the gene in innovation order. Returns false, leaving the set unchanged, if its innovation number is already present.
+    pub fn insert_sorted(&mut self, gene:T) -> bool {
 
         let innov = gene.get_innov();
         if self.hashset.insert(innov) {
@@ -39,7 +43,9 @@ impl<T> VecSet<T> where T: Gene + Clone {
             }
 
             self.vec.insert((index+1) as usize, gene);
+            return true
         } 
+        false
     }
 
     pub fn remove(&mut self, innov:usize) {
@@ -77,6 +83,11 @@ impl<T> VecSet<T> where T: Gene + Clone {
         &self.vec[index]
     }
 
+    pub fn get_by_innov_mut(&mut self, innov:usize) -> &mut T {
+        let index = self.find_innov_index(innov);
+        &mut self.vec[index]
+    }
+
     pub fn get(&self, index:usize) -> &T {
         &self.vec[index]
     }
@@ -85,8 +96,8 @@ impl<T> VecSet<T> where T: Gene + Clone {
     //     self.vec[index].clone()
     // }
 
-    pub fn rand_innov(&self) -> usize {
-        let index = rand::thread_rng().gen_range(0..self.vec.len());
+    pub fn rand_innov<R: Rng + ?Sized>(&self, rng:&mut R) -> usize {
+        let index = rng.gen_range(0..self.vec.len());
         self.vec[index].get_innov()
     }
 
@@ -95,8 +106,8 @@ impl<T> VecSet<T> where T: Gene + Clone {
     //     self.vec.get(index).unwrap()
     // }
 
-    pub fn rand_element_mut(&mut self) -> &mut T {
-        let index = rand::thread_rng().gen_range(0..self.vec.len());
+    pub fn rand_element_mut<R: Rng + ?Sized>(&mut self, rng:&mut R) -> &mut T {
+        let index = rng.gen_range(0..self.vec.len());
         self.vec.get_mut(index).unwrap()
     }
     
@@ -122,3 +133,24 @@ impl<T> VecSet<T> where T: Gene + Clone {
 
 
 }
+
+impl<T> TryFrom<Vec<T>> for VecSet<T> where T: Gene + Clone {
+    type Error = String;
+
+    fn try_from(vec:Vec<T>) -> Result<Self, Self::Error> {
+        let mut res = Self::new();
+        for gene in vec {
+            let innov = gene.get_innov();
+            if !res.push(gene) {
+                return Err(format!("innovation number {} appears more than once", innov))
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl<T> From<VecSet<T>> for Vec<T> {
+    fn from(vecset:VecSet<T>) -> Self {
+        vecset.vec
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Write;
+
+use crate::genetics::{Genome, Gene, NodeType};
+
+const POSITIVE_COLOUR:&str = "#2ca02c";
+const NEGATIVE_COLOUR:&str = "#d62728";
+const MIN_PENWIDTH:f64 = 0.5;
+const MAX_PENWIDTH:f64 = 5.0;
+
+impl Genome {
+
+    /// Returns a Graphviz DOT graph of the genome, drawn left to right.
+    /// Nodes sharing an x coordinate are placed in the same rank, so inputs & bias come first and outputs last.
+    /// Edges are green for positive and red for negative weights, thicker the larger their magnitude, and dashed when disabled.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph genome {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [fontname=\"Helvetica\"];").unwrap();
+
+        for node in self.get_nodes().iter() {
+            let (label, shape) = match node.get_nodetype() {
+                NodeType::Input => ("Input", "box"),
+                NodeType::Bias => ("Bias", "diamond"),
+                NodeType::Output => ("Output", "doublecircle"),
+                NodeType::Hidden => ("Hidden", "circle"),
+            };
+            writeln!(dot, "    n{} [label=\"{} {}\", shape={}];", node.get_innov(), label, node.get_innov(), shape).unwrap();
+        }
+
+        // Group nodes into ranks by x coordinate, ordered from left to right
+        let mut ranks: Vec<(f64, Vec<usize>)> = Vec::new();
+        for node in self.get_nodes().iter() {
+            match ranks.iter_mut().find(|(x, _)| *x == node.get_x()) {
+                Some((_, innovs)) => innovs.push(node.get_innov()),
+                None => ranks.push((node.get_x(), vec![node.get_innov()])),
+            }
+        }
+        ranks.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        for (_, innovs) in ranks.iter() {
+            let members: Vec<String> = innovs.iter().map(|innov| format!("n{};", innov)).collect();
+            writeln!(dot, "    {{ rank=same; {} }}", members.join(" ")).unwrap();
+        }
+        // Invisible edges keep the ranks in x order even where no connection joins them
+        for pair in ranks.windows(2) {
+            writeln!(dot, "    n{} -> n{} [style=invis];", pair[0].1[0], pair[1].1[0]).unwrap();
+        }
+
+        let max_weight = self.get_connections().iter()
+            .map(|conn| conn.get_weight().abs())
+            .fold(0.0, f64::max);
+        for conn in self.get_connections().iter() {
+            let weight = conn.get_weight();
+            let colour = if weight < 0.0 { NEGATIVE_COLOUR } else { POSITIVE_COLOUR };
+            let magnitude = if max_weight > 0.0 { weight.abs() / max_weight } else { 0.0 };
+            let penwidth = MIN_PENWIDTH + (MAX_PENWIDTH - MIN_PENWIDTH) * magnitude;
+            let style = if conn.is_enabled() { "solid" } else { "dashed" };
+            writeln!(dot, "    n{} -> n{} [color=\"{}\", penwidth={:.2}, style={}, tooltip=\"innov {}: {:.3}\"];",
+                conn.get_from(), conn.get_to(), colour, penwidth, style, conn.get_innov(), weight).unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genetics::Genome;
+
+    #[test]
+    fn xor_to_dot() {
+        let mut genome = Genome::new_init_xor();
+        genome.get_connections_mut().rand_element_mut(&mut rand::thread_rng()).set_enabled(false);
+        let dot = genome.to_dot();
+        assert!(dot.starts_with("digraph genome {"));
+        assert!(dot.contains("n0 [label=\"Input 0\", shape=box];"));
+        assert!(dot.contains("n2 [label=\"Bias 2\", shape=diamond];"));
+        assert!(dot.contains("n3 [label=\"Output 3\", shape=doublecircle];"));
+        assert!(dot.contains("{ rank=same; n0; n1; n2; }"));
+        assert!(dot.contains("{ rank=same; n4; n5; }"));
+        assert_eq!(dot.matches("style=dashed").count(), 1);
+        assert_eq!(dot.matches(&format!("color=\"{}\"", super::NEGATIVE_COLOUR)).count(), 3);
+    }
+}
@@ -0,0 +1,2 @@
+mod dot;
+mod svg;
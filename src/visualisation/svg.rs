@@ -0,0 +1,170 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::genetics::{Genome, Gene, NodeType};
+use crate::neural_network::FeedForwardNetwork;
+
+const WIDTH:f64 = 640.0;
+const HEIGHT:f64 = 480.0;
+const MARGIN:f64 = 50.0;
+const RADIUS:f64 = 20.0;
+const MIN_STROKE:f64 = 0.5;
+const MAX_STROKE:f64 = 5.0;
+
+const POSITIVE_COLOUR:(u8, u8, u8) = (44, 160, 44);
+const NEGATIVE_COLOUR:(u8, u8, u8) = (214, 39, 40);
+const EXCITED_COLOUR:(u8, u8, u8) = (255, 176, 0); // fill of a node with activation 1 or more
+const INHIBITED_COLOUR:(u8, u8, u8) = (31, 119, 180); // fill of a node with activation -1 or less
+const INACTIVE_FILL:&str = "#d9d9d9"; // fill of a node the outputs never depended on
+
+fn hex((r, g, b):(u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Blends from white towards colour, by amount in [0, 1].
+fn tint((r, g, b):(u8, u8, u8), amount:f64) -> String {
+    let mix = |c:u8| (255.0 - (255.0 - c as f64) * amount.clamp(0.0, 1.0)).round() as u8;
+    hex((mix(r), mix(g), mix(b)))
+}
+
+fn activation_fill(activation:Option<f64>) -> String {
+    match activation {
+        Some(a) if a >= 0.0 => tint(EXCITED_COLOUR, a),
+        Some(a) => tint(INHIBITED_COLOUR, -a),
+        None => INACTIVE_FILL.to_string(),
+    }
+}
+
+fn nodetype_fill(node_type:NodeType) -> &'static str {
+    match node_type {
+        NodeType::Input => "#00ff2a",
+        NodeType::Bias => "#ff0000",
+        NodeType::Output => "#ffc83d",
+        NodeType::Hidden => "#ffffff",
+    }
+}
+
+impl Genome {
+
+    /// Renders the genome as an SVG image, placing each node by its x & y coordinates.
+    /// Edges are green for positive and red for negative weights, thicker the larger their magnitude, and dashed when disabled.
+    pub fn to_svg(&self) -> String {
+        self.render_svg(None)
+    }
+
+    /// Renders the genome as an SVG image, with each node coloured by its activation when the network is fed inputs.
+    /// Excited nodes are amber, inhibited nodes blue, and nodes the outputs never depended on grey.
+    /// Edges fade with the activation flowing into them, so the paths which fire stand out.
+    pub fn to_svg_activated(&self, inputs:&[f64]) -> String {
+        let mut network = FeedForwardNetwork::new(self);
+        network.activate(inputs.to_vec());
+        self.render_svg(Some(&network))
+    }
+
+    /// Writes Genome::to_svg(), or Genome::to_svg_activated() if inputs are given, to a file.
+    pub fn save_svg<P: AsRef<Path>>(&self, path:P, inputs:Option<&[f64]>) -> io::Result<()> {
+        let svg = match inputs {
+            Some(inputs) => self.to_svg_activated(inputs),
+            None => self.to_svg(),
+        };
+        fs::write(path, svg)
+    }
+
+    fn render_svg(&self, network:Option<&FeedForwardNetwork>) -> String {
+        // Scale node coordinates to fill the canvas. Larger y is drawn higher up.
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for node in self.get_nodes().iter() {
+            min_x = min_x.min(node.get_x());
+            max_x = max_x.max(node.get_x());
+            min_y = min_y.min(node.get_y());
+            max_y = max_y.max(node.get_y());
+        }
+        let scale = |value:f64, min:f64, max:f64, length:f64| {
+            if max > min { (value - min) / (max - min) * (length - 2.0 * MARGIN) } else { (length - 2.0 * MARGIN) / 2.0 }
+        };
+        let position = |innov:usize| {
+            let node = self.get_nodes().get_by_innov(innov);
+            (MARGIN + scale(node.get_x(), min_x, max_x, WIDTH), HEIGHT - MARGIN - scale(node.get_y(), min_y, max_y, HEIGHT))
+        };
+
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"Helvetica, Arial, sans-serif\" font-size=\"12\">", WIDTH, HEIGHT, WIDTH, HEIGHT).unwrap();
+        writeln!(svg, "  <defs>").unwrap();
+        for (name, colour) in [("positive", POSITIVE_COLOUR), ("negative", NEGATIVE_COLOUR)] {
+            writeln!(svg, "    <marker id=\"arrow-{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" markerUnits=\"userSpaceOnUse\" orient=\"auto\"><path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"{}\"/></marker>", name, hex(colour)).unwrap();
+        }
+        writeln!(svg, "  </defs>").unwrap();
+        writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>").unwrap();
+
+        let max_weight = self.get_connections().iter()
+            .map(|conn| conn.get_weight().abs())
+            .fold(0.0, f64::max);
+        for conn in self.get_connections().iter() {
+            let ((x1, y1), (x2, y2)) = (position(conn.get_from()), position(conn.get_to()));
+            let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+            if length <= 2.0 * RADIUS {
+                continue
+            }
+            // Trim the line so it runs from the edge of one circle to the edge of the other
+            let (dx, dy) = ((x2 - x1) / length * RADIUS, (y2 - y1) / length * RADIUS);
+
+            let weight = conn.get_weight();
+            let (name, colour) = if weight < 0.0 { ("negative", NEGATIVE_COLOUR) } else { ("positive", POSITIVE_COLOUR) };
+            let magnitude = if max_weight > 0.0 { weight.abs() / max_weight } else { 0.0 };
+            let stroke = MIN_STROKE + (MAX_STROKE - MIN_STROKE) * magnitude;
+            let opacity = match network {
+                _ if !conn.is_enabled() => 0.4,
+                Some(network) => 0.15 + 0.85 * network.get_activation(conn.get_from()).unwrap_or(0.0).abs().min(1.0),
+                None => 1.0,
+            };
+            let dash = if conn.is_enabled() { "" } else { " stroke-dasharray=\"6 4\"" };
+            writeln!(svg, "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.2}\" stroke-opacity=\"{:.2}\"{} marker-end=\"url(#arrow-{})\"/>",
+                x1 + dx, y1 + dy, x2 - dx, y2 - dy, hex(colour), stroke, opacity, dash, name).unwrap();
+            writeln!(svg, "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"#404040\">{:.3}</text>",
+                (x1 + x2) / 2.0, (y1 + y2) / 2.0 - 4.0, weight).unwrap();
+        }
+
+        for node in self.get_nodes().iter() {
+            let (x, y) = position(node.get_innov());
+            let fill = match network {
+                Some(network) => activation_fill(network.get_activation(node.get_innov())),
+                None => nodetype_fill(node.get_nodetype()).to_string(),
+            };
+            writeln!(svg, "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" stroke=\"#000000\"/>", x, y, RADIUS, fill).unwrap();
+            writeln!(svg, "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>", x, y - RADIUS - 4.0, node.get_innov()).unwrap();
+            if let Some(activation) = network.and_then(|network| network.get_activation(node.get_innov())) {
+                writeln!(svg, "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\">{:.3}</text>", x, y, activation).unwrap();
+            }
+        }
+
+        writeln!(svg, "</svg>").unwrap();
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genetics::Genome;
+    use crate::neural_network::FeedForwardNetwork;
+
+    #[test]
+    fn xor_to_svg() {
+        let genome = Genome::new_init_xor();
+        let svg = genome.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), genome.get_nodes().len());
+        assert_eq!(svg.matches("<line").count(), genome.get_connections().len());
+    }
+
+    #[test]
+    fn activated_svg_shows_outputs() {
+        let genome = Genome::new_init_xor();
+        let output = FeedForwardNetwork::new(&genome).activate(vec![1.0, 0.0])[0];
+        let svg = genome.to_svg_activated(&[1.0, 0.0]);
+        assert!(svg.contains(&format!(">{:.3}</text>", output)));
+        assert!(!svg.contains("#00ff2a"));
+    }
+}